@@ -0,0 +1,82 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use fast_notebook_clear_output::{clear_notebook, clear_notebook_slice, ClearOptions};
+use std::fmt::Write;
+use std::io::{self, BufReader};
+
+/// A notebook written the way Jupyter writes them, with `cells` cells holding a stream output and
+/// an image output of `image_len` base64 bytes each. The cells are tagged `keep_output` if
+/// `tagged`, so that their outputs are kept and written.
+fn notebook(cells: usize, image_len: usize, tagged: bool) -> String {
+    let image = "iVBORw0KGgoAAAANSUhEUgAA".repeat(image_len / 24);
+    let metadata = if tagged {
+        "{\n    \"tags\": [\n     \"keep_output\"\n    ]\n   }"
+    } else {
+        "{}"
+    };
+    let mut notebook = String::from("{\n \"cells\": [\n");
+    for i in 0..cells {
+        let separator = if i + 1 < cells { "," } else { "" };
+        write!(
+            notebook,
+            r#"  {{
+   "cell_type": "code",
+   "execution_count": {i},
+   "id": "cell-{i}",
+   "metadata": {metadata},
+   "outputs": [
+    {{
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "step {i}\n",
+      "loss: 0.1\tdone\n"
+     ]
+    }},
+    {{
+     "data": {{
+      "image/png": "{image}",
+      "text/plain": [
+       "<Figure size 640x480 with 1 Axes>"
+      ]
+     }},
+     "metadata": {{}},
+     "output_type": "display_data"
+    }}
+   ],
+   "source": [
+    "import matplotlib.pyplot as plt\n",
+    "plt.plot([1, 2, 3])"
+   ]
+  }}{separator}
+"#
+        )
+        .unwrap();
+    }
+    notebook.push_str(" ],\n \"metadata\": {},\n \"nbformat\": 4,\n \"nbformat_minor\": 5\n}\n");
+    notebook
+}
+
+fn clear(c: &mut Criterion) {
+    clear_notebooks(c, "clear", &notebook(500, 9600, false));
+    // The outputs are written rather than skipped.
+    clear_notebooks(c, "clear_tagged", &notebook(500, 9600, true));
+}
+
+fn clear_notebooks(c: &mut Criterion, name: &str, notebook: &str) {
+    let options = ClearOptions::default();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(notebook.len() as u64));
+    group.bench_function("buf_read", |b| {
+        b.iter(|| {
+            let reader = BufReader::new(notebook.as_bytes());
+            clear_notebook(reader, io::sink(), &options).unwrap()
+        })
+    });
+    group.bench_function("slice", |b| {
+        b.iter(|| clear_notebook_slice(notebook.as_bytes(), io::sink(), &options).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, clear);
+criterion_main!(benches);
@@ -0,0 +1,125 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use json_event_parser_witespace::{JsonEvent, JsonReader, JsonSliceReader};
+use std::fmt::Write;
+use std::io::BufReader;
+
+/// A notebook written the way Jupyter writes them, with `cells` cells holding a stream output of
+/// `lines` lines and an image output of `image_len` base64 bytes each.
+fn notebook(cells: usize, lines: usize, image_len: usize) -> String {
+    let image = "iVBORw0KGgoAAAANSUhEUgAA".repeat(image_len / 24);
+    let mut notebook = String::from("{\n \"cells\": [\n");
+    for i in 0..cells {
+        let separator = if i + 1 < cells { "," } else { "" };
+        let text = (0..lines)
+            .map(|line| format!("\n      \"loss: 0.{line}\\tdone\\n\""))
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(
+            notebook,
+            r#"  {{
+   "cell_type": "code",
+   "execution_count": {i},
+   "metadata": {{}},
+   "outputs": [
+    {{
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "step {i}\n",{text}
+     ]
+    }},
+    {{
+     "data": {{
+      "image/png": "{image}",
+      "text/plain": [
+       "<Figure size 640x480 with 1 Axes>"
+      ]
+     }},
+     "metadata": {{}},
+     "output_type": "display_data"
+    }}
+   ],
+   "source": [
+    "import matplotlib.pyplot as plt\n",
+    "plt.plot([1, 2, 3])"
+   ]
+  }}{separator}
+"#
+        )
+        .unwrap();
+    }
+    notebook.push_str(" ],\n \"metadata\": {},\n \"nbformat\": 4,\n \"nbformat_minor\": 5\n}\n");
+    notebook
+}
+
+fn read(c: &mut Criterion) {
+    read_notebook(c, "read", &notebook(500, 1, 9600));
+    // Dominated by long strings, and by indentation.
+    read_notebook(c, "read_images", &notebook(20, 1, 1 << 20));
+    read_notebook(c, "read_text", &notebook(500, 200, 0));
+}
+
+fn read_notebook(c: &mut Criterion, name: &str, notebook: &str) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(notebook.len() as u64));
+    group.bench_function("buf_read", |b| {
+        b.iter_batched_ref(
+            || JsonReader::from_reader(BufReader::new(notebook.as_bytes())),
+            |reader| {
+                let mut buffer = Vec::new();
+                while reader.read_event(&mut buffer).unwrap() != JsonEvent::Eof {}
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("slice", |b| {
+        b.iter_batched_ref(
+            || JsonSliceReader::from_slice(notebook.as_bytes()),
+            |reader| while reader.read_event().unwrap() != JsonEvent::Eof {},
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+/// Reads the notebook skipping the outputs of its cells, as when clearing them.
+fn skip(c: &mut Criterion) {
+    let notebook = notebook(500, 1, 9600);
+    let mut group = c.benchmark_group("skip_outputs");
+    group.throughput(Throughput::Bytes(notebook.len() as u64));
+    group.bench_function("buf_read", |b| {
+        b.iter_batched_ref(
+            || JsonReader::from_reader(BufReader::new(notebook.as_bytes())),
+            |reader| {
+                let mut buffer = Vec::new();
+                loop {
+                    match reader.read_event(&mut buffer).unwrap() {
+                        JsonEvent::Eof => break,
+                        JsonEvent::ObjectKey(key) if key == "outputs" => {
+                            reader.skip_value(&mut buffer).unwrap()
+                        }
+                        _ => {}
+                    }
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("slice", |b| {
+        b.iter_batched_ref(
+            || JsonSliceReader::from_slice(notebook.as_bytes()),
+            |reader| loop {
+                match reader.read_event().unwrap() {
+                    JsonEvent::Eof => break,
+                    JsonEvent::ObjectKey(key) if key == "outputs" => reader.skip_value().unwrap(),
+                    _ => {}
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, read, skip);
+criterion_main!(benches);
@@ -0,0 +1,145 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// A position in the input.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct TextPosition {
+    /// Number of bytes before the position.
+    pub offset: u64,
+    /// Line of the position, starting at 1.
+    pub line: u64,
+    /// Column of the position in characters, starting at 1.
+    pub column: u64,
+}
+
+impl Default for TextPosition {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl TextPosition {
+    /// Moves the position past `bytes`.
+    pub(crate) fn advance(&mut self, bytes: &[u8]) {
+        self.offset += bytes.len() as u64;
+        for byte in bytes {
+            if *byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if byte & 0xC0 != 0x80 {
+                // UTF-8 continuation bytes do not start a new character
+                self.column += 1;
+            }
+        }
+    }
+}
+
+impl fmt::Display for TextPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// An error reading or writing JSON.
+#[derive(Debug)]
+pub enum JsonParseError {
+    /// The input ended in the middle of a value.
+    UnexpectedEof {
+        position: TextPosition,
+    },
+    /// A character that is not allowed where it was found.
+    UnexpectedChar {
+        ch: char,
+        /// What was expected instead, such as `',' or '}'`.
+        expected: &'static str,
+        position: TextPosition,
+    },
+    /// An invalid escape sequence in a string, `position` being that of the escaped character.
+    InvalidEscape {
+        position: TextPosition,
+    },
+    /// A string or number that is not valid UTF-8.
+    InvalidUtf8 {
+        position: TextPosition,
+    },
+    /// Content after the end of the JSON value.
+    TrailingContent {
+        position: TextPosition,
+    },
+    /// More nested objects and arrays than allowed by [`JsonReader::max_stack_size`](crate::JsonReader::max_stack_size).
+    StackOverflow {
+        max_stack_size: usize,
+        position: TextPosition,
+    },
+    Io(io::Error),
+}
+
+impl JsonParseError {
+    /// Where the error was found in the input, if it is not an I/O error.
+    pub fn position(&self) -> Option<TextPosition> {
+        match self {
+            Self::UnexpectedEof { position }
+            | Self::UnexpectedChar { position, .. }
+            | Self::InvalidEscape { position }
+            | Self::InvalidUtf8 { position }
+            | Self::TrailingContent { position }
+            | Self::StackOverflow { position, .. } => Some(*position),
+            Self::Io(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for JsonParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof { position } => {
+                write!(f, "Unexpected end of input at {}", position)
+            }
+            Self::UnexpectedChar {
+                ch,
+                expected,
+                position,
+            } => write!(
+                f,
+                "Unexpected char {:?} at {}, expected {}",
+                ch, position, expected
+            ),
+            Self::InvalidEscape { position } => {
+                write!(f, "Invalid string escape at {}", position)
+            }
+            Self::InvalidUtf8 { position } => write!(f, "Invalid UTF-8 at {}", position),
+            Self::TrailingContent { position } => {
+                write!(f, "JSON trailing content at {}", position)
+            }
+            Self::StackOverflow {
+                max_stack_size,
+                position,
+            } => write!(
+                f,
+                "Max stack size of {} reached at {}",
+                max_stack_size, position
+            ),
+            Self::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for JsonParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for JsonParseError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
@@ -16,7 +16,7 @@ pub enum JsonEvent<'a> {
     EndObject,
     ObjectKey(Cow<'a, str>),
     Eof,
-    WhiteSpace(String),
+    WhiteSpace(Cow<'a, str>),
 }
 
 impl<'a> JsonEvent<'a> {
@@ -35,8 +35,49 @@ impl<'a> JsonEvent<'a> {
             JsonEvent::EndObject => JsonEvent::EndObject,
             JsonEvent::ObjectKey(s) => JsonEvent::ObjectKey(Cow::Owned(s.to_string())),
             JsonEvent::Eof => JsonEvent::Eof,
-            JsonEvent::WhiteSpace(s) => JsonEvent::WhiteSpace(s.to_string()),
+            JsonEvent::WhiteSpace(s) => JsonEvent::WhiteSpace(whitespace(s.as_bytes())),
             JsonEvent::NextArrayValue => JsonEvent::NextArrayValue,
         }
     }
 }
+
+const INDENTATION_BYTES: [u8; 129] = {
+    let mut indentation = [b' '; 129];
+    indentation[0] = b'\n';
+    indentation
+};
+/// A newline and spaces, which the whitespace of indented JSON is mostly a prefix or suffix of.
+const INDENTATION: &str = match std::str::from_utf8(&INDENTATION_BYTES) {
+    Ok(indentation) => indentation,
+    Err(_) => panic!("the indentation is ASCII"),
+};
+
+/// The JSON whitespace `bytes`, borrowed from [`INDENTATION`] to not be allocated when possible.
+pub(crate) fn whitespace(bytes: &[u8]) -> Cow<'static, str> {
+    let (start, spaces) = match bytes.strip_prefix(b"\n") {
+        Some(spaces) => (0, spaces),
+        None => (1, bytes),
+    };
+    if spaces.len() < INDENTATION.len() && spaces.iter().all(|c| *c == b' ') {
+        Cow::Borrowed(&INDENTATION[start..start + bytes.len()])
+    } else {
+        // JSON whitespace is ASCII
+        Cow::Owned(bytes.iter().copied().map(char::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrow_indentation() {
+        for space in ["\n", "\n    ", " ", ""] {
+            assert!(matches!(whitespace(space.as_bytes()), Cow::Borrowed(s) if s == space));
+        }
+        let long = format!("\n{}", " ".repeat(200));
+        for space in ["\r\n ", "\t", "  \n", long.as_str()] {
+            assert!(matches!(whitespace(space.as_bytes()), Cow::Owned(s) if s == space));
+        }
+    }
+}
@@ -1,7 +1,12 @@
+mod error;
 mod event;
 mod read;
+mod scan;
+mod slice;
 mod write;
 
+pub use error::{JsonParseError, TextPosition};
 pub use event::JsonEvent;
 pub use read::JsonReader;
+pub use slice::JsonSliceReader;
 pub use write::JsonWriter;
@@ -1,25 +1,33 @@
 #![allow(dead_code)]
 
-use crate::event::JsonEvent;
-use anyhow::{anyhow, Result};
+use crate::error::{JsonParseError, TextPosition};
+use crate::event::{whitespace, JsonEvent};
+use crate::scan::{find_string_end, skip_whitespaces};
+use memchr::memchr2;
+use smallvec::SmallVec;
 use std::borrow::Cow;
 use std::collections::VecDeque;
-use std::io::{BufRead, Error, ErrorKind, Seek, SeekFrom};
+use std::io::{self, BufRead, ErrorKind, Read};
 use std::str;
 
+type Result<T> = std::result::Result<T, JsonParseError>;
+
 #[allow(dead_code)]
 pub struct JsonReader<R> {
-    reader: R,
+    reader: PositionReader<R>,
     state_stack: Vec<JsonState>,
     element_read: bool,
     remain_events: VecDeque<JsonEvent<'static>>,
     max_stack_size: Option<usize>,
 }
 
-impl<R: BufRead + Seek> JsonReader<R> {
+impl<R: BufRead> JsonReader<R> {
     pub fn from_reader(reader: R) -> Self {
         Self {
-            reader,
+            reader: PositionReader {
+                inner: reader,
+                position: TextPosition::default(),
+            },
             state_stack: Vec::new(),
             element_read: false,
             max_stack_size: None,
@@ -33,6 +41,14 @@ impl<R: BufRead + Seek> JsonReader<R> {
         self
     }
 
+    /// Position in the input of the next byte to read.
+    ///
+    /// Events are read ahead to attach whitespace, so after an event is returned the position
+    /// may be past the whitespace following it.
+    pub fn position(&self) -> TextPosition {
+        self.reader.position
+    }
+
     pub fn read_event<'a>(&mut self, buffer: &'a mut Vec<u8>) -> Result<JsonEvent<'a>> {
         if let Some(ev) = self.remain_events.pop_front() {
             return Ok(ev);
@@ -43,10 +59,20 @@ impl<R: BufRead + Seek> JsonReader<R> {
                 if self.state_stack.is_empty() && self.element_read {
                     Ok(JsonEvent::Eof)
                 } else {
-                    Err(anyhow!(Error::from(ErrorKind::UnexpectedEof)))
+                    Err(self.unexpected_eof())
                 }
             }
             SkipWhitespace::Skip(whitespaces, _) => Ok(JsonEvent::WhiteSpace(whitespaces)),
+            SkipWhitespace::NoSkip(_) if self.state_stack.is_empty() && self.element_read => {
+                Err(JsonParseError::TrailingContent {
+                    position: self.reader.position,
+                })
+            }
+            SkipWhitespace::NoSkip(front)
+                if front != b'"' && expects_key(self.state_stack.last(), front) =>
+            {
+                Err(unexpected_char(&mut self.reader, "a string key"))
+            }
             SkipWhitespace::NoSkip(front) => match front {
                 b'{' => {
                     self.reader.consume(1);
@@ -55,17 +81,15 @@ impl<R: BufRead + Seek> JsonReader<R> {
                     Ok(JsonEvent::StartObject)
                 }
                 b'}' => {
-                    self.reader.consume(1);
                     if matches!(
-                        self.state_stack.pop(),
+                        self.state_stack.last(),
                         Some(JsonState::FirstObjectKey) | Some(JsonState::LastObjectKey)
                     ) {
+                        self.reader.consume(1);
+                        self.state_stack.pop();
                         self.read_after_value(JsonEvent::EndObject, buffer)
                     } else {
-                        Err(anyhow!(Error::new(
-                            ErrorKind::InvalidData,
-                            "Closing a not opened object",
-                        )))
+                        Err(unexpected_char(&mut self.reader, "a value"))
                     }
                 }
                 b'[' => {
@@ -75,17 +99,15 @@ impl<R: BufRead + Seek> JsonReader<R> {
                     Ok(JsonEvent::StartArray)
                 }
                 b']' => {
-                    self.reader.consume(1);
                     if matches!(
-                        self.state_stack.pop(),
+                        self.state_stack.last(),
                         Some(JsonState::FirstArray) | Some(JsonState::LastArray)
                     ) {
+                        self.reader.consume(1);
+                        self.state_stack.pop();
                         self.read_after_value(JsonEvent::EndArray, buffer)
                     } else {
-                        Err(anyhow!(Error::new(
-                            ErrorKind::InvalidData,
-                            "Closing a not opened array",
-                        )))
+                        Err(unexpected_char(&mut self.reader, "a value"))
                     }
                 }
                 b'"' => self.parse_string(buffer),
@@ -93,19 +115,136 @@ impl<R: BufRead + Seek> JsonReader<R> {
                 b'f' => self.parse_constant::<5>("false", JsonEvent::Boolean(false), buffer),
                 b'n' => self.parse_constant::<4>("null", JsonEvent::Null, buffer),
                 b'-' | b'0'..=b'9' => self.parse_number(front, buffer),
-                c => {
+                _ => Err(unexpected_char(&mut self.reader, "a value")),
+            },
+        }
+    }
+
+    /// Skips the next value and the whitespace before it without reading its events.
+    ///
+    /// Only the brackets of the value are checked: strings are not decoded and numbers and
+    /// constants are only delimited, which makes skipping much faster than reading for large
+    /// strings such as embedded images. The events after the value are read as if it had been.
+    ///
+    /// # Panics
+    ///
+    /// If a value is not expected next, like after an `ObjectKey` event or in an array.
+    pub fn skip_value(&mut self, buffer: &mut Vec<u8>) -> Result<()> {
+        assert!(
+            self.remain_events.is_empty()
+                && !matches!(
+                    self.state_stack.last(),
+                    Some(JsonState::FirstObjectKey) | Some(JsonState::NextObjectKey)
+                ),
+            "a value is not expected next"
+        );
+        loop {
+            let buf = self.fill_buf()?;
+            let skipped = skip_whitespaces(buf);
+            let end = skipped < buf.len() || buf.is_empty();
+            self.reader.consume(skipped);
+            if end {
+                break;
+            }
+        }
+        if self.state_stack.is_empty() && self.element_read {
+            return Err(JsonParseError::TrailingContent {
+                position: self.reader.position,
+            });
+        }
+        // The closing brackets of the open objects and arrays.
+        let mut closing = SmallVec::<[u8; 16]>::new();
+        loop {
+            let Some(c) = self.lookup_front()? else {
+                return Err(self.unexpected_eof());
+            };
+            match c {
+                b'{' | b'[' => {
                     self.reader.consume(1);
-                    Err(anyhow!(Error::new(
-                        ErrorKind::InvalidData,
-                        format!("Unexpected char: {}", char::from(c)),
-                    )))
+                    closing.push(if c == b'{' { b'}' } else { b']' });
+                    if let Some(max_stack_size) = self.max_stack_size {
+                        if self.state_stack.len() + closing.len() > max_stack_size + 1 {
+                            return Err(JsonParseError::StackOverflow {
+                                max_stack_size,
+                                position: self.reader.position,
+                            });
+                        }
+                    }
                 }
-            },
+                b'}' | b']' if closing.last() == Some(&c) => {
+                    self.reader.consume(1);
+                    closing.pop();
+                }
+                b'}' | b']' if closing.is_empty() => {
+                    return Err(unexpected_char(&mut self.reader, "a value"))
+                }
+                b'}' => return Err(unexpected_char(&mut self.reader, "']'")),
+                b']' => return Err(unexpected_char(&mut self.reader, "'}'")),
+                b'"' => self.skip_string()?,
+                _ if !closing.is_empty() => {
+                    // Separators, whitespace and scalars are not checked in a skipped container.
+                    let buf = self.fill_buf()?;
+                    let len = 1 + buf[1..]
+                        .iter()
+                        .position(|c| matches!(c, b'{' | b'}' | b'[' | b']' | b'"'))
+                        .unwrap_or(buf.len() - 1);
+                    self.reader.consume(len);
+                }
+                b'-' | b'0'..=b'9' | b't' | b'f' | b'n' => loop {
+                    let buf = self.fill_buf()?;
+                    let delimiter = buf.iter().position(|c| {
+                        matches!(c, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r')
+                    });
+                    let len = delimiter.unwrap_or(buf.len());
+                    self.reader.consume(len);
+                    if delimiter.is_some() || len == 0 {
+                        break;
+                    }
+                },
+                _ => return Err(unexpected_char(&mut self.reader, "a value")),
+            }
+            if closing.is_empty() {
+                break;
+            }
+        }
+        self.read_after_value(JsonEvent::Null, buffer).map(drop)
+    }
+
+    /// Skips a string without decoding it.
+    fn skip_string(&mut self) -> Result<()> {
+        self.reader.consume(1);
+        let mut escape = false;
+        loop {
+            let buf = self.fill_buf()?;
+            if buf.is_empty() {
+                return Err(self.unexpected_eof());
+            }
+            if escape {
+                // The escaped character may be a quote, and is not checked.
+                escape = false;
+                self.reader.consume(1);
+                continue;
+            }
+            match memchr2(b'"', b'\\', buf) {
+                Some(i) if buf[i] == b'"' => {
+                    self.reader.consume(i + 1);
+                    return Ok(());
+                }
+                Some(i) => {
+                    escape = true;
+                    self.reader.consume(i + 1);
+                }
+                None => {
+                    let len = buf.len();
+                    self.reader.consume(len);
+                }
+            }
         }
     }
 
     fn parse_string<'a>(&mut self, output: &'a mut Vec<u8>) -> Result<JsonEvent<'a>> {
         output.clear();
+        let start = self.reader.position;
         self.reader.consume(1);
 
         #[derive(Eq, PartialEq, Copy, Clone)]
@@ -122,7 +261,7 @@ impl<R: BufRead + Seek> JsonReader<R> {
                     let buffer = match self.reader.fill_buf() {
                         Ok(buf) => {
                             if buf.is_empty() {
-                                return Err(anyhow!(Error::from(ErrorKind::UnexpectedEof)));
+                                return Err(self.unexpected_eof());
                             } else {
                                 buf
                             }
@@ -131,41 +270,48 @@ impl<R: BufRead + Seek> JsonReader<R> {
                             if e.kind() == ErrorKind::Interrupted {
                                 continue;
                             } else {
-                                return Err(anyhow!(e));
+                                return Err(e.into());
                             }
                         }
                     };
-                    let mut i = 0;
-                    for c in buffer {
-                        i += 1;
-                        match *c {
-                            b'"' => {
-                                self.reader.consume(i);
-                                return self.read_after_value(
-                                    JsonEvent::String(Cow::Owned(
-                                        String::from_utf8(output.clone())
-                                            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
-                                    )),
-                                    output,
-                                );
-                            }
-                            b'\\' => {
-                                state = StringState::Escape;
-                                break;
-                            }
-                            0..=0x1F => {
-                                self.reader.consume(i);
-                                return Err(anyhow!(Error::new(
-                                    ErrorKind::InvalidData,
-                                    "Control characters are not allowed in JSON",
-                                )));
+                    match find_string_end(buffer) {
+                        Some(i) => {
+                            let c = buffer[i];
+                            output.extend_from_slice(&buffer[..i]);
+                            match c {
+                                b'"' => {
+                                    self.reader.consume(i + 1);
+                                    return self.read_after_value(
+                                        JsonEvent::String(Cow::Owned(
+                                            String::from_utf8(output.clone()).map_err(|_| {
+                                                JsonParseError::InvalidUtf8 { position: start }
+                                            })?,
+                                        )),
+                                        output,
+                                    );
+                                }
+                                b'\\' => {
+                                    self.reader.consume(i + 1);
+                                    state = StringState::Escape;
+                                }
+                                _ => {
+                                    self.reader.consume(i);
+                                    return Err(unexpected_char(
+                                        &mut self.reader,
+                                        "a string character",
+                                    ));
+                                }
                             }
-                            c => output.push(c),
+                        }
+                        None => {
+                            output.extend_from_slice(buffer);
+                            let len = buffer.len();
+                            self.reader.consume(len);
                         }
                     }
-                    self.reader.consume(i);
                 }
                 StringState::Escape => {
+                    let escape_position = self.reader.position;
                     let c = self.lookup_mandatory_front()?;
                     self.reader.consume(1);
                     match c {
@@ -195,45 +341,36 @@ impl<R: BufRead + Seek> JsonReader<R> {
                         }
                         b'u' => {
                             let mut buf = [0u8; 4];
-                            self.reader.read_exact(&mut buf)?;
-                            let code_point = read_hexa_char(&buf)?;
+                            self.read_exact(&mut buf)?;
+                            let code_point = read_hexa_char(&buf, escape_position)?;
                             if let Some(c) = char::from_u32(code_point) {
                                 output.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
                             } else {
                                 let high_surrogate = code_point;
                                 let mut buf = [0u8; 6];
-                                self.reader.read_exact(&mut buf)?;
+                                self.read_exact(&mut buf)?;
                                 if !buf.starts_with(b"\\u") {
-                                    return Err(anyhow!(Error::new(
-                                        ErrorKind::InvalidData,
-                                        format!(
-                                            "\\u{:X} is a surrogate should be followed by an other surrogate",
-                                            high_surrogate
-                                        )),
-                                    ));
+                                    return Err(JsonParseError::InvalidEscape {
+                                        position: escape_position,
+                                    });
                                 }
-                                let low_surrogate = read_hexa_char(&buf[2..])?;
+                                let low_surrogate = read_hexa_char(&buf[2..], escape_position)?;
                                 let code_point = 0x10000
                                     + ((high_surrogate & 0x03FF) << 10)
                                     + (low_surrogate & 0x03FF);
                                 if let Some(c) = char::from_u32(code_point) {
                                     output.extend_from_slice(c.encode_utf8(&mut buf).as_bytes())
                                 } else {
-                                    return Err(anyhow!(Error::new(
-                                        ErrorKind::InvalidData,
-                                        format!(
-                                            "\\u{:X}\\u{:X} is an invalid surrogate pair",
-                                            high_surrogate, low_surrogate
-                                        ),
-                                    )));
+                                    return Err(JsonParseError::InvalidEscape {
+                                        position: escape_position,
+                                    });
                                 }
                             }
                         }
                         _ => {
-                            return Err(anyhow!(Error::new(
-                                ErrorKind::InvalidData,
-                                "Invalid string escape",
-                            )));
+                            return Err(JsonParseError::InvalidEscape {
+                                position: escape_position,
+                            });
                         }
                     }
                     state = StringState::Default;
@@ -244,24 +381,29 @@ impl<R: BufRead + Seek> JsonReader<R> {
 
     fn parse_constant<'a, const SIZE: usize>(
         &mut self,
-        expected: &str,
+        expected: &'static str,
         value: JsonEvent<'a>,
         buffer: &mut Vec<u8>,
     ) -> Result<JsonEvent<'a>> {
         debug_assert_eq!(expected.len(), SIZE);
+        let position = self.reader.position;
         let mut buf = [0u8; SIZE];
-        self.reader.read_exact(&mut buf)?;
+        self.read_exact(&mut buf)?;
         if buf == expected.as_bytes() {
             self.read_after_value(value, buffer)
         } else {
-            Err(anyhow!(Error::new(
-                ErrorKind::InvalidData,
-                format!(
-                    "{} expected, found {}",
-                    expected,
-                    str::from_utf8(&buf).map_err(|e| Error::new(ErrorKind::InvalidData, e))?
-                ),
-            )))
+            let mismatch = buf
+                .iter()
+                .zip(expected.as_bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let mut position = position;
+            position.advance(&buf[..mismatch]);
+            Err(JsonParseError::UnexpectedChar {
+                ch: first_char(&buf[mismatch..]),
+                expected,
+                position,
+            })
         }
     }
 
@@ -288,19 +430,14 @@ impl<R: BufRead + Seek> JsonReader<R> {
                 self.reader.consume(1);
                 self.read_digits(output)?;
             }
-            _ => {
-                return Err(anyhow!(Error::new(
-                    ErrorKind::InvalidData,
-                    "Invalid number"
-                )))
-            }
+            _ => return Err(unexpected_char(&mut self.reader, "a digit")),
         }
 
         // Dot
         if self.lookup_front()? == Some(b'.') {
             output.push(b'.');
             self.reader.consume(1);
-            self.read_char(|c| matches!(c, b'0'..=b'9'), output)?;
+            self.read_char(|c: u8| c.is_ascii_digit(), output)?;
             self.read_digits(output)?;
         }
 
@@ -314,28 +451,24 @@ impl<R: BufRead + Seek> JsonReader<R> {
                     b'-' | b'+' => {
                         output.push(c);
                         self.reader.consume(1);
-                        self.read_char(|c| matches!(c, b'0'..=b'9'), output)?;
+                        self.read_char(|c: u8| c.is_ascii_digit(), output)?;
                     }
                     b'0'..=b'9' => {
                         output.push(c);
                         self.reader.consume(1);
                     }
-                    _ => {
-                        return Err(anyhow!(Error::new(
-                            ErrorKind::InvalidData,
-                            format!("Invalid number. Found char {}", char::from(c)),
-                        )))
-                    }
+                    _ => return Err(unexpected_char(&mut self.reader, "a digit")),
                 }
                 self.read_digits(output)?;
             }
         }
 
         self.read_after_value(
-            JsonEvent::Number(Cow::Owned(
-                String::from_utf8(output.clone())
-                    .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
-            )),
+            JsonEvent::Number(Cow::Owned(String::from_utf8(output.clone()).map_err(
+                |_| JsonParseError::InvalidUtf8 {
+                    position: self.reader.position,
+                },
+            )?)),
             output,
         )
     }
@@ -347,16 +480,13 @@ impl<R: BufRead + Seek> JsonReader<R> {
             self.reader.consume(1);
             Ok(())
         } else {
-            Err(anyhow!(Error::new(
-                ErrorKind::InvalidData,
-                format!("Invalid number. Found char {}", char::from(c)),
-            )))
+            Err(unexpected_char(&mut self.reader, "a digit"))
         }
     }
 
     fn read_digits(&mut self, output: &mut Vec<u8>) -> Result<()> {
         while let Some(c) = self.lookup_front()? {
-            if matches!(c, b'0'..=b'9') {
+            if c.is_ascii_digit() {
                 output.push(c);
                 self.reader.consume(1);
             } else {
@@ -380,6 +510,7 @@ impl<R: BufRead + Seek> JsonReader<R> {
         type SkipMatchValue<'a> = (JsonEvent<'a>, bool, Option<JsonEvent<'static>>);
         let mut skip_with_match_before =
             |skip_whitespace,
+             end_position: TextPosition,
              match_func: Box<dyn FnOnce(Option<u8>) -> Result<SkipMatchValue<'a>>>| {
                 match skip_whitespace {
                     SkipWhitespace::NoSkip(front) => {
@@ -403,10 +534,9 @@ impl<R: BufRead + Seek> JsonReader<R> {
                             Ok(event.into_owned())
                         }
                     }
-                    SkipWhitespace::EmptyBuffer => Err(anyhow!(Error::new(
-                        ErrorKind::UnexpectedEof,
-                        "Unexpected end of input while parsing JSON",
-                    ))),
+                    SkipWhitespace::EmptyBuffer => Err(JsonParseError::UnexpectedEof {
+                        position: end_position,
+                    }),
                 }
             };
 
@@ -414,29 +544,24 @@ impl<R: BufRead + Seek> JsonReader<R> {
             Some(JsonState::FirstObjectKey) | Some(JsonState::NextObjectKey) => {
                 skip_with_match_before(
                     lookup_front_segment_whitespaces_impl(reader, buffer)?,
+                    reader.position,
                     Box::new(|front| {
                         if front == Some(b':') {
                             self.reader.consume(1);
                             self.state_stack.push(JsonState::ObjectValue);
-                            if let JsonEvent::String(value) = value {
-                                Ok((JsonEvent::ObjectKey(value), true, None))
-                            } else {
-                                Err(anyhow!(Error::new(
-                                    ErrorKind::InvalidData,
-                                    "Object keys should strings",
-                                )))
-                            }
+                            let JsonEvent::String(value) = value else {
+                                unreachable!("object keys are checked to start with '\"'")
+                            };
+                            Ok((JsonEvent::ObjectKey(value), true, None))
                         } else {
-                            Err(anyhow!(Error::new(
-                                ErrorKind::InvalidData,
-                                "Object keys should be followed by ':'",
-                            )))
+                            Err(unexpected_char(&mut self.reader, "':'"))
                         }
                     }),
                 )
             }
             Some(JsonState::ObjectValue) => skip_with_match_before(
                 lookup_front_segment_whitespaces_impl(reader, buffer)?,
+                reader.position,
                 Box::new(|front| match front {
                     Some(b',') => {
                         self.reader.consume(1);
@@ -447,14 +572,12 @@ impl<R: BufRead + Seek> JsonReader<R> {
                         self.state_stack.push(JsonState::LastObjectKey);
                         Ok((value, false, None))
                     }
-                    _ => Err(anyhow!(Error::new(
-                        ErrorKind::InvalidData,
-                        "Object values should be followed by ',' or '}'",
-                    ))),
+                    _ => Err(unexpected_char(&mut self.reader, "',' or '}'")),
                 }),
             ),
             Some(JsonState::FirstArray) | Some(JsonState::NextArray) => skip_with_match_before(
                 lookup_front_segment_whitespaces_impl(reader, buffer)?,
+                reader.position,
                 Box::new(|front| match front {
                     Some(b',') => {
                         self.reader.consume(1);
@@ -465,59 +588,21 @@ impl<R: BufRead + Seek> JsonReader<R> {
                         self.state_stack.push(JsonState::LastArray);
                         Ok((value, false, None))
                     }
-                    _ => Err(anyhow!(Error::new(
-                        ErrorKind::InvalidData,
-                        "Array values should be followed by ',' or ']'",
-                    ))),
+                    _ => Err(unexpected_char(&mut self.reader, "',' or ']'")),
                 }),
             ),
             None => {
                 if self.element_read {
-                    Err(anyhow!(Error::new(
-                        ErrorKind::InvalidData,
-                        "JSON trailing content"
-                    )))
+                    Err(JsonParseError::TrailingContent {
+                        position: self.reader.position,
+                    })
                 } else {
                     self.element_read = true;
                     Ok(value)
                 }
             }
-            Some(JsonState::LastObjectKey) => Err(anyhow!(Error::new(
-                ErrorKind::InvalidData,
-                "JSON object elements should be separated by commas",
-            ))),
-            Some(JsonState::LastArray) => Err(anyhow!(Error::new(
-                ErrorKind::InvalidData,
-                "JSON array elements should be separated by commas",
-            ))),
-        }
-    }
-
-    fn peek_front_skipping_whitespaces(&mut self) -> Result<Option<u8>> {
-        let mut back_pos = None;
-        loop {
-            match self.reader.fill_buf() {
-                Ok(buf) => {
-                    if buf.is_empty() {
-                        return Ok(None);
-                    }
-                    let skipped = skip_whitespaces(buf);
-                    if skipped == buf.len() {
-                        back_pos = Some(self.reader.stream_position()?);
-                        self.reader.consume(skipped);
-                    } else {
-                        let result = Some(buf[skipped]);
-                        if let Some(bp) = back_pos {
-                            self.reader.seek(SeekFrom::Start(bp))?;
-                        }
-                        return Ok(result);
-                    }
-                }
-                Err(error) => {
-                    if error.kind() != ErrorKind::Interrupted {
-                        return Err(anyhow!(error));
-                    }
-                }
+            Some(JsonState::LastObjectKey) | Some(JsonState::LastArray) => {
+                unreachable!("closing brackets are read right after the last value")
             }
         }
     }
@@ -530,33 +615,51 @@ impl<R: BufRead + Seek> JsonReader<R> {
         if let Some(v) = self.lookup_front()? {
             Ok(v)
         } else {
-            Err(anyhow!(Error::from(ErrorKind::UnexpectedEof)))
+            Err(self.unexpected_eof())
+        }
+    }
+
+    /// Reads exactly `buf.len()` bytes, failing with the position on a too short input.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.reader.read_exact(buf).map_err(|e| {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                self.unexpected_eof()
+            } else {
+                e.into()
+            }
+        })
+    }
+
+    fn unexpected_eof(&self) -> JsonParseError {
+        JsonParseError::UnexpectedEof {
+            position: self.reader.position,
         }
     }
 
     fn lookup_front(&mut self) -> Result<Option<u8>> {
+        Ok(self.fill_buf()?.first().copied())
+    }
+
+    /// The buffered input, empty at the end of the input.
+    fn fill_buf(&mut self) -> Result<&[u8]> {
         loop {
             match self.reader.fill_buf() {
-                Ok(buf) => return Ok(if buf.is_empty() { None } else { Some(buf[0]) }),
-                Err(error) => {
-                    if error.kind() != ErrorKind::Interrupted {
-                        return Err(anyhow!(error));
-                    }
-                }
+                Ok(_) => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error.into()),
             }
         }
+        // The buffer is filled already, and only borrowed again to be returned out of the loop
+        Ok(self.reader.fill_buf()?)
     }
 
     fn check_stack_size(&self) -> Result<()> {
         if let Some(max_stack_size) = self.max_stack_size {
             if self.state_stack.len() > max_stack_size {
-                Err(anyhow!(Error::new(
-                    ErrorKind::InvalidData,
-                    format!(
-                        "Max stack size of {} reached on an object opening",
-                        max_stack_size
-                    ),
-                )))
+                Err(JsonParseError::StackOverflow {
+                    max_stack_size,
+                    position: self.reader.position,
+                })
             } else {
                 Ok(())
             }
@@ -568,7 +671,7 @@ impl<R: BufRead + Seek> JsonReader<R> {
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 #[allow(dead_code)]
-enum JsonState {
+pub(crate) enum JsonState {
     FirstArray,
     NextArray,
     LastArray,
@@ -578,16 +681,53 @@ enum JsonState {
     ObjectValue,
 }
 
-#[allow(dead_code)]
-fn skip_whitespaces(buf: &[u8]) -> usize {
-    for (i, c) in buf.iter().enumerate() {
-        if !matches!(c, b' ' | b'\t' | b'\n' | b'\r') {
-            return i;
-        }
+/// Whether an object key is expected in `state`, `front` not closing an empty object.
+pub(crate) fn expects_key(state: Option<&JsonState>, front: u8) -> bool {
+    match state {
+        Some(JsonState::FirstObjectKey) => front != b'}',
+        Some(JsonState::NextObjectKey) => true,
+        _ => false,
     }
-    buf.len()
 }
-fn read_hexa_char(input: &[u8]) -> Result<u32> {
+
+/// An error for the character at the front of `reader`, or for the end of the input.
+fn unexpected_char<R: BufRead>(
+    reader: &mut PositionReader<R>,
+    expected: &'static str,
+) -> JsonParseError {
+    let position = reader.position;
+    let len = match reader.fill_buf() {
+        Ok([]) => return JsonParseError::UnexpectedEof { position },
+        Ok(buf) => match buf[0] {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        },
+        Err(error) => return error.into(),
+    };
+    // The character may not be entirely in the buffer, and the input is not read further anyway
+    let mut bytes = [0; 4];
+    let ch = match reader.read_exact(&mut bytes[..len]) {
+        Ok(()) => first_char(&bytes[..len]),
+        Err(_) => char::REPLACEMENT_CHARACTER,
+    };
+    JsonParseError::UnexpectedChar {
+        ch,
+        expected,
+        position,
+    }
+}
+
+/// The character `bytes` start with, or U+FFFD if they do not start with valid UTF-8.
+pub(crate) fn first_char(bytes: &[u8]) -> char {
+    String::from_utf8_lossy(&bytes[..bytes.len().min(4)])
+        .chars()
+        .next()
+        .unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+pub(crate) fn read_hexa_char(input: &[u8], position: TextPosition) -> Result<u32> {
     let mut value = 0;
     for c in input.iter().copied() {
         value = value * 16
@@ -595,18 +735,13 @@ fn read_hexa_char(input: &[u8]) -> Result<u32> {
                 b'0'..=b'9' => u32::from(c) - u32::from(b'0'),
                 b'a'..=b'f' => u32::from(c) - u32::from(b'a') + 10,
                 b'A'..=b'F' => u32::from(c) - u32::from(b'A') + 10,
-                _ => {
-                    return Err(anyhow!(Error::new(
-                        ErrorKind::InvalidData,
-                        "Unexpected character in a unicode escape",
-                    )))
-                }
+                _ => return Err(JsonParseError::InvalidEscape { position }),
             }
     }
     Ok(value)
 }
 
-fn lookup_front_segment_whitespaces_impl<R: BufRead + Seek>(
+fn lookup_front_segment_whitespaces_impl<R: BufRead>(
     reader: &mut R,
     output: &mut Vec<u8>,
 ) -> Result<SkipWhitespace> {
@@ -618,10 +753,7 @@ fn lookup_front_segment_whitespaces_impl<R: BufRead + Seek>(
                     return if output.is_empty() {
                         Ok(SkipWhitespace::EmptyBuffer)
                     } else {
-                        Ok(SkipWhitespace::Skip(
-                            String::from_utf8(output.clone())?,
-                            None,
-                        ))
+                        Ok(SkipWhitespace::Skip(whitespace(output), None))
                     };
                 }
                 let skipped = skip_whitespaces(buf);
@@ -636,31 +768,59 @@ fn lookup_front_segment_whitespaces_impl<R: BufRead + Seek>(
                     return if output.is_empty() {
                         Ok(SkipWhitespace::NoSkip(c))
                     } else {
-                        Ok(SkipWhitespace::Skip(
-                            String::from_utf8(output.clone())?,
-                            Some(c),
-                        ))
+                        Ok(SkipWhitespace::Skip(whitespace(output), Some(c)))
                     };
                 }
             }
             Err(error) => {
                 if error.kind() != ErrorKind::Interrupted {
-                    return Err(anyhow!(error));
+                    return Err(error.into());
                 }
             }
         }
     }
 }
 
+/// Tracks the position of the bytes consumed from `inner`.
+struct PositionReader<R> {
+    inner: R,
+    position: TextPosition,
+}
+
+impl<R: BufRead> Read for PositionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position.advance(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for PositionReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if amt > 0 {
+            if let Ok(buf) = self.inner.fill_buf() {
+                self.position.advance(&buf[..amt.min(buf.len())]);
+            }
+        }
+        self.inner.consume(amt);
+    }
+}
+
 enum SkipWhitespace {
     EmptyBuffer,
     NoSkip(u8),
-    Skip(String, Option<u8>),
+    Skip(Cow<'static, str>, Option<u8>),
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::JsonSliceReader;
+    use rstest::rstest;
     use std::io::{BufReader, Cursor};
 
     #[test]
@@ -682,4 +842,215 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn read_without_seek() {
+        let json_str = "{\n  \"key\" :  [1,   \"value\"]  \n}\n";
+
+        let mut events = vec![];
+        let mut reader = JsonReader::from_reader(BufReader::with_capacity(1, json_str.as_bytes()));
+        let mut buffer = Vec::new();
+        loop {
+            let ev = reader.read_event(&mut buffer).unwrap().into_owned();
+            if ev == JsonEvent::Eof {
+                break;
+            }
+            events.push(ev);
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                JsonEvent::StartObject,
+                JsonEvent::WhiteSpace(Cow::Borrowed("\n  ")),
+                JsonEvent::WhiteSpace(Cow::Borrowed(" ")),
+                JsonEvent::ObjectKey(Cow::Borrowed("key")),
+                JsonEvent::WhiteSpace(Cow::Borrowed("  ")),
+                JsonEvent::StartArray,
+                JsonEvent::Number(Cow::Borrowed("1")),
+                JsonEvent::NextArrayValue,
+                JsonEvent::WhiteSpace(Cow::Borrowed("   ")),
+                JsonEvent::String(Cow::Borrowed("value")),
+                JsonEvent::EndArray,
+                JsonEvent::WhiteSpace(Cow::Borrowed("  \n")),
+                JsonEvent::EndObject,
+                JsonEvent::WhiteSpace(Cow::Borrowed("\n")),
+            ]
+        );
+    }
+
+    fn read_error(json_str: &str, capacity: usize) -> JsonParseError {
+        let mut reader =
+            JsonReader::from_reader(BufReader::with_capacity(capacity, json_str.as_bytes()));
+        let mut buffer = Vec::new();
+        loop {
+            match reader.read_event(&mut buffer) {
+                Ok(JsonEvent::Eof) => panic!("{:?} should be invalid", json_str),
+                Ok(_) => {}
+                Err(error) => return error,
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(
+        "{\"a\": 1 \"b\": 2}",
+        "Unexpected char '\"' at line 1, column 9, expected ',' or '}'",
+        8
+    )]
+    #[case(
+        "[\n  1,\n  2 3]",
+        "Unexpected char '3' at line 3, column 5, expected ',' or ']'",
+        11
+    )]
+    #[case("{\"é\": \"\\x\"}", "Invalid string escape at line 1, column 9", 9)]
+    #[case(
+        "[\"a\nb\"]",
+        "Unexpected char '\\n' at line 1, column 4, expected a string character",
+        3
+    )]
+    #[case(
+        "[1, -x]",
+        "Unexpected char 'x' at line 1, column 6, expected a digit",
+        5
+    )]
+    #[case("[tru]", "Unexpected char ']' at line 1, column 5, expected true", 4)]
+    #[case(
+        "{\"a\": é}",
+        "Unexpected char 'é' at line 1, column 7, expected a value",
+        6
+    )]
+    #[case(
+        "{\"a\": 1,}",
+        "Unexpected char '}' at line 1, column 9, expected a string key",
+        8
+    )]
+    #[case(
+        "{1: 2}",
+        "Unexpected char '1' at line 1, column 2, expected a string key",
+        1
+    )]
+    #[case(
+        "[1}",
+        "Unexpected char '}' at line 1, column 3, expected ',' or ']'",
+        2
+    )]
+    #[case("{\n \"a\": [1,\n", "Unexpected end of input at line 3, column 1", 12)]
+    #[case("{} {}", "JSON trailing content at line 1, column 4", 3)]
+    fn error_positions(
+        #[case] json_str: &str,
+        #[case] message: &str,
+        #[case] offset: u64,
+        #[values(1, 8192)] capacity: usize,
+    ) {
+        let error = read_error(json_str, capacity);
+        assert_eq!(error.to_string(), message);
+        assert_eq!(error.position().unwrap().offset, offset);
+    }
+
+    /// The events or error of reading `json_str`, skipping the values of the keys in `skipped`.
+    fn read_skipping(
+        json_str: &str,
+        capacity: usize,
+        skipped: &[&str],
+    ) -> (Vec<JsonEvent<'static>>, Option<String>) {
+        let mut reader =
+            JsonReader::from_reader(BufReader::with_capacity(capacity, json_str.as_bytes()));
+        let mut buffer = Vec::new();
+        let mut events = vec![];
+        loop {
+            match reader
+                .read_event(&mut buffer)
+                .map(|event| event.into_owned())
+            {
+                Ok(JsonEvent::Eof) => return (events, None),
+                Ok(event) => {
+                    let skip = matches!(&event, JsonEvent::ObjectKey(key) if skipped.contains(&key.as_ref()));
+                    events.push(event);
+                    if skip {
+                        if let Err(error) = reader.skip_value(&mut buffer) {
+                            return (events, Some(error.to_string()));
+                        }
+                    }
+                }
+                Err(error) => return (events, Some(error.to_string())),
+            }
+        }
+    }
+
+    #[rstest]
+    fn skip_values(
+        #[values(
+            r#"{"a": {"b": [1, "x\"]{", {}], "c": null}, "d": 2}"#,
+            "{\"a\":\n  [\"\\\\\", -1.5e3, true] ,\"d\": 2}",
+            r#"{"a": "é\u00e9", "d": 2}"#,
+            r#"{"a": -12, "d": 2}"#,
+            r#"{"d": 2, "a": false}"#,
+            r#"{"a": [1}, "d": 2}"#,
+            r#"{"a": {"b": 1]}"#,
+            r#"{"a": }"#,
+            r#"{"a": x}"#,
+            r#"{"a": [1, 2"#,
+            r#"{"a": "abc\"}"#,
+            r#"{"a": [] 1}"#
+        )]
+        json_str: &str,
+        #[values(1, 8192)] capacity: usize,
+    ) {
+        let mut reader = JsonSliceReader::from_slice(json_str.as_bytes());
+        let mut expected = vec![];
+        let expected_error = loop {
+            match reader.read_event() {
+                Ok(JsonEvent::Eof) => break None,
+                Ok(event) => {
+                    let skip = matches!(&event, JsonEvent::ObjectKey(key) if key == "a");
+                    expected.push(event.into_owned());
+                    if skip {
+                        if let Err(error) = reader.skip_value() {
+                            break Some(error.to_string());
+                        }
+                    }
+                }
+                Err(error) => break Some(error.to_string()),
+            }
+        };
+        assert_eq!(
+            read_skipping(json_str, capacity, &["a"]),
+            (expected, expected_error)
+        );
+    }
+
+    #[test]
+    fn error_kinds() {
+        assert!(matches!(
+            read_error("[1, 2", 8192),
+            JsonParseError::UnexpectedEof { .. }
+        ));
+        assert!(matches!(
+            read_error("[1] 2", 8192),
+            JsonParseError::TrailingContent { .. }
+        ));
+        assert!(matches!(
+            read_error("[\"\\ud800abcdef\"]", 8192),
+            JsonParseError::InvalidEscape { .. }
+        ));
+
+        let mut reader = JsonReader::from_reader("[[[[1]]]]".as_bytes());
+        reader.max_stack_size(2);
+        let mut buffer = Vec::new();
+        let error = loop {
+            match reader.read_event(&mut buffer) {
+                Ok(JsonEvent::Eof) => panic!("the stack should overflow"),
+                Ok(_) => {}
+                Err(error) => break error,
+            }
+        };
+        assert!(matches!(
+            error,
+            JsonParseError::StackOverflow {
+                max_stack_size: 2,
+                ..
+            }
+        ));
+    }
 }
@@ -0,0 +1,200 @@
+//! Vectorized scanning of whitespace runs and string contents, the reader hot paths.
+//!
+//! On x86_64, 16 bytes are compared at once with SSE2, which is part of the architecture, and
+//! long strings 32 bytes at once with AVX2 when the CPU supports it. Other targets use the
+//! scalar loops.
+
+/// The length of the whitespace run at the start of `buf`.
+pub(crate) fn skip_whitespaces(buf: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        x86::skip_whitespaces(buf)
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        scalar::skip_whitespaces(buf)
+    }
+}
+
+/// The index of the first `"`, `\` or control character of `buf`, which end the part of a
+/// string that is copied as is.
+pub(crate) fn find_string_end(buf: &[u8]) -> Option<usize> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        x86::find_string_end(buf)
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        scalar::find_string_end(buf)
+    }
+}
+
+mod scalar {
+    pub fn skip_whitespaces(buf: &[u8]) -> usize {
+        buf.iter()
+            .position(|c| !matches!(c, b' ' | b'\t' | b'\n' | b'\r'))
+            .unwrap_or(buf.len())
+    }
+
+    pub fn find_string_end(buf: &[u8]) -> Option<usize> {
+        buf.iter()
+            .position(|c| matches!(c, b'"' | b'\\' | 0..=0x1F))
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::scalar;
+    use std::arch::x86_64::*;
+
+    /// Strings at least this long after their first 16 bytes are worth the AVX2 call.
+    const AVX2_MIN_LEN: usize = 64;
+
+    pub fn skip_whitespaces(buf: &[u8]) -> usize {
+        // Most runs are a newline and some indentation, found in the first chunk.
+        let mut i = 0;
+        while i + 16 <= buf.len() {
+            // SAFETY: SSE2 is part of x86_64, and the 16 bytes from `i` are in `buf`.
+            let chunk = unsafe { _mm_loadu_si128(buf.as_ptr().add(i).cast()) };
+            let mask = !whitespace_mask(chunk) & 0xFFFF;
+            if mask != 0 {
+                return i + mask.trailing_zeros() as usize;
+            }
+            i += 16;
+        }
+        i + scalar::skip_whitespaces(&buf[i..])
+    }
+
+    pub fn find_string_end(buf: &[u8]) -> Option<usize> {
+        if buf.len() >= 16 {
+            // Keys and short values end in the first chunk.
+            // SAFETY: SSE2 is part of x86_64, and the first 16 bytes are in `buf`.
+            let chunk = unsafe { _mm_loadu_si128(buf.as_ptr().cast()) };
+            let mask = string_end_mask(chunk);
+            if mask != 0 {
+                return Some(mask.trailing_zeros() as usize);
+            }
+            if buf.len() - 16 >= AVX2_MIN_LEN && is_x86_feature_detected!("avx2") {
+                // SAFETY: AVX2 is supported by the CPU.
+                return unsafe { avx2_find_string_end(&buf[16..]) }.map(|i| i + 16);
+            }
+            return sse2_find_string_end(&buf[16..]).map(|i| i + 16);
+        }
+        scalar::find_string_end(buf)
+    }
+
+    pub fn sse2_find_string_end(buf: &[u8]) -> Option<usize> {
+        let mut i = 0;
+        while i + 16 <= buf.len() {
+            // SAFETY: SSE2 is part of x86_64, and the 16 bytes from `i` are in `buf`.
+            let chunk = unsafe { _mm_loadu_si128(buf.as_ptr().add(i).cast()) };
+            let mask = string_end_mask(chunk);
+            if mask != 0 {
+                return Some(i + mask.trailing_zeros() as usize);
+            }
+            i += 16;
+        }
+        scalar::find_string_end(&buf[i..]).map(|j| i + j)
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn avx2_find_string_end(buf: &[u8]) -> Option<usize> {
+        let quote = _mm256_set1_epi8(b'"' as i8);
+        let backslash = _mm256_set1_epi8(b'\\' as i8);
+        let control = _mm256_set1_epi8(0x1F);
+        let mut i = 0;
+        while i + 32 <= buf.len() {
+            // SAFETY: the 32 bytes from `i` are in `buf`.
+            let chunk = unsafe { _mm256_loadu_si256(buf.as_ptr().add(i).cast()) };
+            let special = _mm256_or_si256(
+                _mm256_or_si256(
+                    _mm256_cmpeq_epi8(chunk, quote),
+                    _mm256_cmpeq_epi8(chunk, backslash),
+                ),
+                // Unsigned `chunk <= 0x1F`.
+                _mm256_cmpeq_epi8(_mm256_min_epu8(chunk, control), chunk),
+            );
+            let mask = _mm256_movemask_epi8(special) as u32;
+            if mask != 0 {
+                return Some(i + mask.trailing_zeros() as usize);
+            }
+            i += 32;
+        }
+        sse2_find_string_end(&buf[i..]).map(|j| i + j)
+    }
+
+    /// A bit set for each whitespace byte of `chunk`.
+    #[inline]
+    fn whitespace_mask(chunk: __m128i) -> u32 {
+        // SAFETY: SSE2 is part of x86_64.
+        unsafe {
+            let space = _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b' ' as i8));
+            let newline = _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\n' as i8));
+            let tab = _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\t' as i8));
+            let carriage_return = _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\r' as i8));
+            let whitespace = _mm_or_si128(
+                _mm_or_si128(space, newline),
+                _mm_or_si128(tab, carriage_return),
+            );
+            _mm_movemask_epi8(whitespace) as u32
+        }
+    }
+
+    /// A bit set for each `"`, `\` or control byte of `chunk`.
+    #[inline]
+    fn string_end_mask(chunk: __m128i) -> u32 {
+        // SAFETY: SSE2 is part of x86_64.
+        unsafe {
+            let quote = _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'"' as i8));
+            let backslash = _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\\' as i8));
+            // Unsigned `chunk <= 0x1F`.
+            let control = _mm_cmpeq_epi8(_mm_min_epu8(chunk, _mm_set1_epi8(0x1F)), chunk);
+            _mm_movemask_epi8(_mm_or_si128(_mm_or_si128(quote, backslash), control)) as u32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Inputs of every length up to a few chunks, with `special` at each position among `fill`.
+    fn inputs(fill: &[u8], special: u8) -> impl Iterator<Item = Vec<u8>> + '_ {
+        (0..150).flat_map(move |len| {
+            let base = fill.iter().copied().cycle().take(len).collect::<Vec<_>>();
+            (0..=len).map(move |at| {
+                let mut input = base.clone();
+                if at < len {
+                    input[at] = special;
+                }
+                input
+            })
+        })
+    }
+
+    #[test]
+    fn whitespaces() {
+        for special in [b'a', b'"', 0, 0x0B, 0x80, 0xA0, 0xFF] {
+            for input in inputs(b" \n\t\r  ", special) {
+                assert_eq!(
+                    skip_whitespaces(&input),
+                    scalar::skip_whitespaces(&input),
+                    "{input:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn string_ends() {
+        // Bytes from 0x80 would be below 0x20 in a signed comparison.
+        for special in [b'"', b'\\', 0, b'\n', 0x1F, b' ', 0x7F, 0x80, 0x9F, 0xFF] {
+            for input in inputs(b"iVBORw0KGgo+/= \xC3\xA9", special) {
+                let expected = scalar::find_string_end(&input);
+                assert_eq!(find_string_end(&input), expected, "{input:?}");
+                #[cfg(target_arch = "x86_64")]
+                assert_eq!(x86::sse2_find_string_end(&input), expected, "{input:?}");
+            }
+        }
+    }
+}
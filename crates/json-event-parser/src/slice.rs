@@ -0,0 +1,722 @@
+use crate::error::{JsonParseError, TextPosition};
+use crate::event::JsonEvent;
+use crate::read::{expects_key, first_char, read_hexa_char, JsonState};
+use crate::scan::{find_string_end, skip_whitespaces};
+use memchr::memchr2;
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::str;
+
+type Result<T> = std::result::Result<T, JsonParseError>;
+
+/// A reader of JSON already in memory, such as a file read at once or mapped in memory.
+///
+/// It reads the same events as [`JsonReader`](crate::JsonReader), but strings, keys and numbers
+/// borrow from the input instead of being copied into a buffer. Only strings with escapes are
+/// allocated.
+pub struct JsonSliceReader<'input> {
+    input: &'input [u8],
+    offset: usize,
+    /// The last position computed, positions only being needed for errors and on demand.
+    position: Cell<TextPosition>,
+    state_stack: Vec<JsonState>,
+    element_read: bool,
+    remain_events: VecDeque<JsonEvent<'input>>,
+    max_stack_size: Option<usize>,
+}
+
+impl<'input> JsonSliceReader<'input> {
+    pub fn from_slice(input: &'input [u8]) -> Self {
+        Self {
+            input,
+            offset: 0,
+            position: Cell::new(TextPosition::default()),
+            state_stack: Vec::new(),
+            element_read: false,
+            remain_events: VecDeque::new(),
+            max_stack_size: None,
+        }
+    }
+
+    /// Maximal allowed number of nested object and array openings. Infinite by default.
+    pub fn max_stack_size(&mut self, size: usize) -> &mut Self {
+        self.max_stack_size = Some(size);
+        self
+    }
+
+    /// Position in the input of the next byte to read.
+    ///
+    /// Events are read ahead to attach whitespace, so after an event is returned the position
+    /// may be past the whitespace following it.
+    pub fn position(&self) -> TextPosition {
+        self.position_at(self.offset)
+    }
+
+    pub fn read_event(&mut self) -> Result<JsonEvent<'input>> {
+        if let Some(ev) = self.remain_events.pop_front() {
+            return Ok(ev);
+        }
+
+        let whitespace = self.skip_whitespace();
+        if !whitespace.is_empty() {
+            return Ok(JsonEvent::WhiteSpace(Cow::Borrowed(whitespace)));
+        }
+        let Some(&front) = self.input.get(self.offset) else {
+            return if self.state_stack.is_empty() && self.element_read {
+                Ok(JsonEvent::Eof)
+            } else {
+                Err(self.unexpected_char("a value"))
+            };
+        };
+        if self.state_stack.is_empty() && self.element_read {
+            return Err(JsonParseError::TrailingContent {
+                position: self.position(),
+            });
+        }
+        if front != b'"' && expects_key(self.state_stack.last(), front) {
+            return Err(self.unexpected_char("a string key"));
+        }
+        match front {
+            b'{' => {
+                self.offset += 1;
+                self.check_stack_size()?;
+                self.state_stack.push(JsonState::FirstObjectKey);
+                Ok(JsonEvent::StartObject)
+            }
+            b'}' => {
+                if matches!(
+                    self.state_stack.last(),
+                    Some(JsonState::FirstObjectKey) | Some(JsonState::LastObjectKey)
+                ) {
+                    self.offset += 1;
+                    self.state_stack.pop();
+                    self.read_after_value(JsonEvent::EndObject)
+                } else {
+                    Err(self.unexpected_char("a value"))
+                }
+            }
+            b'[' => {
+                self.offset += 1;
+                self.check_stack_size()?;
+                self.state_stack.push(JsonState::FirstArray);
+                Ok(JsonEvent::StartArray)
+            }
+            b']' => {
+                if matches!(
+                    self.state_stack.last(),
+                    Some(JsonState::FirstArray) | Some(JsonState::LastArray)
+                ) {
+                    self.offset += 1;
+                    self.state_stack.pop();
+                    self.read_after_value(JsonEvent::EndArray)
+                } else {
+                    Err(self.unexpected_char("a value"))
+                }
+            }
+            b'"' => {
+                let string = self.parse_string()?;
+                self.read_after_value(JsonEvent::String(string))
+            }
+            b't' => self.parse_constant("true", JsonEvent::Boolean(true)),
+            b'f' => self.parse_constant("false", JsonEvent::Boolean(false)),
+            b'n' => self.parse_constant("null", JsonEvent::Null),
+            b'-' | b'0'..=b'9' => {
+                let number = self.parse_number()?;
+                self.read_after_value(JsonEvent::Number(Cow::Borrowed(number)))
+            }
+            _ => Err(self.unexpected_char("a value")),
+        }
+    }
+
+    /// Skips the next value and the whitespace before it without reading its events.
+    ///
+    /// Only the brackets of the value are checked: strings are not decoded and numbers and
+    /// constants are only delimited, which makes skipping much faster than reading for large
+    /// strings such as embedded images. The events after the value are read as if it had been.
+    ///
+    /// # Panics
+    ///
+    /// If a value is not expected next, like after an `ObjectKey` event or in an array.
+    pub fn skip_value(&mut self) -> Result<()> {
+        assert!(
+            self.remain_events.is_empty()
+                && !matches!(
+                    self.state_stack.last(),
+                    Some(JsonState::FirstObjectKey) | Some(JsonState::NextObjectKey)
+                ),
+            "a value is not expected next"
+        );
+        self.skip_whitespace();
+        if self.state_stack.is_empty() && self.element_read {
+            return Err(JsonParseError::TrailingContent {
+                position: self.position(),
+            });
+        }
+        // The closing brackets of the open objects and arrays.
+        let mut closing = SmallVec::<[u8; 16]>::new();
+        loop {
+            let Some(&c) = self.input.get(self.offset) else {
+                return Err(self.unexpected_char("a value"));
+            };
+            match c {
+                b'{' | b'[' => {
+                    self.offset += 1;
+                    closing.push(if c == b'{' { b'}' } else { b']' });
+                    if let Some(max_stack_size) = self.max_stack_size {
+                        if self.state_stack.len() + closing.len() > max_stack_size + 1 {
+                            return Err(JsonParseError::StackOverflow {
+                                max_stack_size,
+                                position: self.position(),
+                            });
+                        }
+                    }
+                }
+                b'}' | b']' if closing.last() == Some(&c) => {
+                    self.offset += 1;
+                    closing.pop();
+                }
+                b'}' | b']' if closing.is_empty() => return Err(self.unexpected_char("a value")),
+                b'}' => return Err(self.unexpected_char("']'")),
+                b']' => return Err(self.unexpected_char("'}'")),
+                b'"' => self.skip_string()?,
+                _ if !closing.is_empty() => {
+                    // Separators, whitespace and scalars are not checked in a skipped container.
+                    self.offset += 1 + self.input[self.offset + 1..]
+                        .iter()
+                        .position(|c| matches!(c, b'{' | b'}' | b'[' | b']' | b'"'))
+                        .unwrap_or(self.input.len() - self.offset - 1);
+                }
+                b'-' | b'0'..=b'9' | b't' | b'f' | b'n' => {
+                    self.offset += self.input[self.offset..]
+                        .iter()
+                        .position(|c| {
+                            matches!(c, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r')
+                        })
+                        .unwrap_or(self.input.len() - self.offset);
+                }
+                _ => return Err(self.unexpected_char("a value")),
+            }
+            if closing.is_empty() {
+                break;
+            }
+        }
+        self.read_after_value(JsonEvent::Null).map(drop)
+    }
+
+    /// Skips a string without decoding it.
+    fn skip_string(&mut self) -> Result<()> {
+        self.offset += 1;
+        loop {
+            match memchr2(b'"', b'\\', &self.input[self.offset..]) {
+                Some(i) if self.input[self.offset + i] == b'"' => {
+                    self.offset += i + 1;
+                    return Ok(());
+                }
+                Some(i) => self.offset = (self.offset + i + 2).min(self.input.len()),
+                None => {
+                    self.offset = self.input.len();
+                    return Err(self.unexpected_char("a string character"));
+                }
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<Cow<'input, str>> {
+        let start = self.offset;
+        self.offset += 1;
+        let end = find_string_end(&self.input[self.offset..]).map(|i| self.offset + i);
+        match end.map(|end| (end, self.input[end])) {
+            Some((end, b'"')) => {
+                let string = self.utf8(&self.input[self.offset..end], start)?;
+                self.offset = end + 1;
+                Ok(Cow::Borrowed(string))
+            }
+            Some((end, b'\\')) => {
+                let mut output = self.input[self.offset..end].to_vec();
+                self.offset = end;
+                self.parse_escaped_string(&mut output)?;
+                String::from_utf8(output)
+                    .map(Cow::Owned)
+                    .map_err(|_| JsonParseError::InvalidUtf8 {
+                        position: self.position_at(start),
+                    })
+            }
+            Some((end, _)) => {
+                self.offset = end;
+                Err(self.unexpected_char("a string character"))
+            }
+            None => {
+                self.offset = self.input.len();
+                Err(self.unexpected_char("a string character"))
+            }
+        }
+    }
+
+    /// Reads the rest of a string from its first escape.
+    fn parse_escaped_string(&mut self, output: &mut Vec<u8>) -> Result<()> {
+        loop {
+            let Some(&c) = self.input.get(self.offset) else {
+                return Err(self.unexpected_char("a string character"));
+            };
+            match c {
+                b'"' => {
+                    self.offset += 1;
+                    return Ok(());
+                }
+                b'\\' => {
+                    self.offset += 1;
+                    self.parse_escape(output)?;
+                }
+                0..=0x1F => return Err(self.unexpected_char("a string character")),
+                _ => {
+                    let end = find_string_end(&self.input[self.offset..])
+                        .map_or(self.input.len(), |i| self.offset + i);
+                    output.extend_from_slice(&self.input[self.offset..end]);
+                    self.offset = end;
+                }
+            }
+        }
+    }
+
+    /// Reads an escape sequence after its backslash.
+    fn parse_escape(&mut self, output: &mut Vec<u8>) -> Result<()> {
+        let escape_offset = self.offset;
+        let invalid_escape = |reader: &Self| JsonParseError::InvalidEscape {
+            position: reader.position_at(escape_offset),
+        };
+        let Some(&c) = self.input.get(self.offset) else {
+            return Err(self.unexpected_char("an escape"));
+        };
+        self.offset += 1;
+        let escaped = match c {
+            b'"' => b'"',
+            b'\\' => b'\\',
+            b'/' => b'/',
+            b'b' => 8,
+            b'f' => 12,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'u' => {
+                let escape_position = self.position_at(escape_offset);
+                let code_point = read_hexa_char(self.take(4)?, escape_position)?;
+                let c = match char::from_u32(code_point) {
+                    Some(c) => c,
+                    None => {
+                        let high_surrogate = code_point;
+                        let low = self.take(6)?;
+                        if !low.starts_with(b"\\u") {
+                            return Err(invalid_escape(self));
+                        }
+                        let low_surrogate = read_hexa_char(&low[2..], escape_position)?;
+                        let code_point =
+                            0x10000 + ((high_surrogate & 0x03FF) << 10) + (low_surrogate & 0x03FF);
+                        char::from_u32(code_point).ok_or_else(|| invalid_escape(self))?
+                    }
+                };
+                output.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                return Ok(());
+            }
+            _ => return Err(invalid_escape(self)),
+        };
+        output.push(escaped);
+        Ok(())
+    }
+
+    fn parse_constant(
+        &mut self,
+        expected: &'static str,
+        value: JsonEvent<'input>,
+    ) -> Result<JsonEvent<'input>> {
+        let bytes = self.take(expected.len())?;
+        if bytes == expected.as_bytes() {
+            self.read_after_value(value)
+        } else {
+            let mismatch = bytes
+                .iter()
+                .zip(expected.as_bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            self.offset -= expected.len() - mismatch;
+            Err(self.unexpected_char(expected))
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<&'input str> {
+        let start = self.offset;
+        if self.front() == Some(b'-') {
+            self.offset += 1;
+        }
+        match self.front() {
+            Some(b'0') => self.offset += 1,
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => return Err(self.unexpected_char("a digit")),
+        }
+
+        // Dot
+        if self.front() == Some(b'.') {
+            self.offset += 1;
+            self.read_digits()?;
+        }
+
+        // Exp
+        if matches!(self.front(), Some(b'e' | b'E')) {
+            self.offset += 1;
+            if matches!(self.front(), Some(b'-' | b'+')) {
+                self.offset += 1;
+            }
+            self.read_digits()?;
+        }
+
+        Ok(str::from_utf8(&self.input[start..self.offset]).expect("numbers are ASCII"))
+    }
+
+    /// Reads at least one digit.
+    fn read_digits(&mut self) -> Result<()> {
+        if self.front().is_some_and(|c| c.is_ascii_digit()) {
+            self.skip_digits();
+            Ok(())
+        } else {
+            Err(self.unexpected_char("a digit"))
+        }
+    }
+
+    fn skip_digits(&mut self) {
+        self.offset += self.input[self.offset..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+    }
+
+    fn read_after_value(&mut self, value: JsonEvent<'input>) -> Result<JsonEvent<'input>> {
+        match self.state_stack.pop() {
+            Some(JsonState::FirstObjectKey) | Some(JsonState::NextObjectKey) => {
+                let whitespace = self.skip_whitespace();
+                if self.front() != Some(b':') {
+                    return Err(self.unexpected_char("':'"));
+                }
+                self.offset += 1;
+                self.state_stack.push(JsonState::ObjectValue);
+                let JsonEvent::String(key) = value else {
+                    unreachable!("object keys are checked to start with '\"'")
+                };
+                if whitespace.is_empty() {
+                    Ok(JsonEvent::ObjectKey(key))
+                } else {
+                    self.remain_events.push_back(JsonEvent::ObjectKey(key));
+                    Ok(JsonEvent::WhiteSpace(Cow::Borrowed(whitespace)))
+                }
+            }
+            Some(JsonState::ObjectValue) => self.read_separator(
+                value,
+                b'}',
+                (JsonState::NextObjectKey, JsonState::LastObjectKey),
+                JsonEvent::NextObjectValue,
+                "',' or '}'",
+            ),
+            Some(JsonState::FirstArray) | Some(JsonState::NextArray) => self.read_separator(
+                value,
+                b']',
+                (JsonState::NextArray, JsonState::LastArray),
+                JsonEvent::NextArrayValue,
+                "',' or ']'",
+            ),
+            None => {
+                if self.element_read {
+                    Err(JsonParseError::TrailingContent {
+                        position: self.position(),
+                    })
+                } else {
+                    self.element_read = true;
+                    Ok(value)
+                }
+            }
+            Some(JsonState::LastObjectKey) | Some(JsonState::LastArray) => {
+                unreachable!("closing brackets are read right after the last value")
+            }
+        }
+    }
+
+    /// Reads the `,` or the closing bracket after `value`, queuing the events after it.
+    fn read_separator(
+        &mut self,
+        value: JsonEvent<'input>,
+        close: u8,
+        (next_state, last_state): (JsonState, JsonState),
+        separator: JsonEvent<'input>,
+        expected: &'static str,
+    ) -> Result<JsonEvent<'input>> {
+        let whitespace = self.skip_whitespace();
+        let front = self.front();
+        if front == Some(b',') {
+            self.offset += 1;
+            self.state_stack.push(next_state);
+        } else if front == Some(close) {
+            self.state_stack.push(last_state);
+        } else {
+            return Err(self.unexpected_char(expected));
+        }
+        if !whitespace.is_empty() {
+            self.remain_events
+                .push_back(JsonEvent::WhiteSpace(Cow::Borrowed(whitespace)));
+        }
+        if front == Some(b',') {
+            self.remain_events.push_back(separator);
+        }
+        Ok(value)
+    }
+
+    /// Skips the whitespace at the front of the input, returning it.
+    fn skip_whitespace(&mut self) -> &'input str {
+        let input = self.input;
+        let start = self.offset;
+        self.offset += skip_whitespaces(&input[start..]);
+        str::from_utf8(&input[start..self.offset]).expect("JSON whitespace is ASCII")
+    }
+
+    fn front(&self) -> Option<u8> {
+        self.input.get(self.offset).copied()
+    }
+
+    /// Takes the next `len` bytes, failing at the end of the input if there are fewer.
+    fn take(&mut self, len: usize) -> Result<&'input [u8]> {
+        let input = self.input;
+        if input.len() - self.offset < len {
+            self.offset = input.len();
+            return Err(JsonParseError::UnexpectedEof {
+                position: self.position(),
+            });
+        }
+        self.offset += len;
+        Ok(&input[self.offset - len..self.offset])
+    }
+
+    fn utf8(&self, bytes: &'input [u8], start: usize) -> Result<&'input str> {
+        str::from_utf8(bytes).map_err(|_| JsonParseError::InvalidUtf8 {
+            position: self.position_at(start),
+        })
+    }
+
+    /// An error for the character at the front of the input, or for the end of the input.
+    fn unexpected_char(&self, expected: &'static str) -> JsonParseError {
+        let position = self.position();
+        match &self.input[self.offset..] {
+            [] => JsonParseError::UnexpectedEof { position },
+            rest => JsonParseError::UnexpectedChar {
+                ch: first_char(rest),
+                expected,
+                position,
+            },
+        }
+    }
+
+    fn check_stack_size(&self) -> Result<()> {
+        match self.max_stack_size {
+            Some(max_stack_size) if self.state_stack.len() > max_stack_size => {
+                Err(JsonParseError::StackOverflow {
+                    max_stack_size,
+                    position: self.position(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The position of `offset`, advancing from the last position computed when possible.
+    fn position_at(&self, offset: usize) -> TextPosition {
+        let mut position = self.position.get();
+        if position.offset as usize > offset {
+            position = TextPosition::default();
+        }
+        position.advance(&self.input[position.offset as usize..offset]);
+        self.position.set(position);
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonReader;
+    use rstest::rstest;
+
+    fn read_all(json_str: &str) -> (Vec<JsonEvent<'static>>, Option<String>) {
+        let mut reader = JsonReader::from_reader(json_str.as_bytes());
+        let mut buffer = Vec::new();
+        let mut events = vec![];
+        loop {
+            match reader.read_event(&mut buffer) {
+                Ok(JsonEvent::Eof) => return (events, None),
+                Ok(event) => events.push(event.into_owned()),
+                Err(error) => return (events, Some(error.to_string())),
+            }
+        }
+    }
+
+    fn read_all_slice(json_str: &str) -> (Vec<JsonEvent<'_>>, Option<String>) {
+        let mut reader = JsonSliceReader::from_slice(json_str.as_bytes());
+        let mut events = vec![];
+        loop {
+            match reader.read_event() {
+                Ok(JsonEvent::Eof) => return (events, None),
+                Ok(event) => events.push(event),
+                Err(error) => return (events, Some(error.to_string())),
+            }
+        }
+    }
+
+    #[rstest]
+    #[case("{\n  \"key\" :  [1,   \"value\"]  \n}\n")]
+    #[case(include_str!("../assets/notebook/sample.ipynb"))]
+    #[case("[-0.5e+10, 12E3, true, false, null, {}, [], \"\"]")]
+    #[case(r#"{"a\"b": "\u00e9\ud83d\ude00\n\\\/", "é": [ {} , [ ] ] }"#)]
+    #[case("{\"a\": 1 \"b\": 2}")]
+    #[case("{\"a\" 1}")]
+    #[case("{\"a\": 1,}")]
+    #[case("[1}")]
+    #[case("[1, tru]")]
+    #[case("[nul")]
+    #[case("[-]")]
+    #[case("[1.]")]
+    #[case("[1e]")]
+    #[case("[\"a\nb\"]")]
+    #[case("[\"\\q\"]")]
+    #[case("[\"\\ud800abcdef\"]")]
+    #[case("[\"\\u12\"]")]
+    #[case("[\"abc")]
+    #[case("{\n \"a\": [1,\n")]
+    #[case("{} {}")]
+    #[case("1 2")]
+    #[case("{\"a\": é}")]
+    #[case("")]
+    fn same_events_as_reader(#[case] json_str: &str) {
+        let (events, error) = read_all(json_str);
+        let (slice_events, slice_error) = read_all_slice(json_str);
+        assert_eq!(slice_events, events);
+        assert_eq!(slice_error, error);
+    }
+
+    #[test]
+    fn borrow_unescaped_strings() {
+        let (events, _) = read_all_slice(r#"{"key": ["value", "esc\naped", 1.5]}"#);
+        assert!(matches!(
+            &events[1],
+            JsonEvent::ObjectKey(Cow::Borrowed("key"))
+        ));
+        assert!(matches!(
+            &events[4],
+            JsonEvent::String(Cow::Borrowed("value"))
+        ));
+        assert!(matches!(&events[7], JsonEvent::String(Cow::Owned(s)) if s == "esc\naped"));
+        assert!(matches!(
+            &events[10],
+            JsonEvent::Number(Cow::Borrowed("1.5"))
+        ));
+    }
+
+    /// The events of `json_str`, skipping the values of the keys in `skipped`.
+    fn read_skipping(json_str: &str, skipped: &[&str]) -> Result<Vec<JsonEvent<'static>>> {
+        let mut reader = JsonSliceReader::from_slice(json_str.as_bytes());
+        let mut events = vec![];
+        loop {
+            match reader.read_event()? {
+                JsonEvent::Eof => return Ok(events),
+                JsonEvent::ObjectKey(key) if skipped.contains(&key.as_ref()) => {
+                    reader.skip_value()?;
+                    events.push(JsonEvent::ObjectKey(key.into_owned().into()));
+                }
+                event => events.push(event.into_owned()),
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(r#"{"a": {"b": [1, "x\"]{", {}], "c": null}, "d": 2}"#)]
+    #[case("{\"a\":\n  [\"\\\\\", -1.5e3, true] ,\"d\": 2}")]
+    #[case(r#"{"a": "é\u00e9", "d": 2}"#)]
+    #[case(r#"{"a": -12, "d": 2}"#)]
+    #[case(r#"{"d": 2, "a": false}"#)]
+    fn skip_values(#[case] json_str: &str) {
+        // The events without the value of `a` and the whitespace before it.
+        let (mut events, _) = read_all_slice(json_str);
+        let i = events
+            .iter()
+            .position(|event| matches!(event, JsonEvent::ObjectKey(key) if key == "a"))
+            .unwrap()
+            + 1;
+        let mut depth = 0;
+        loop {
+            match events.remove(i) {
+                JsonEvent::WhiteSpace(_) if depth == 0 => continue,
+                JsonEvent::StartObject | JsonEvent::StartArray => depth += 1,
+                JsonEvent::EndObject | JsonEvent::EndArray => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+        }
+        assert_eq!(read_skipping(json_str, &["a"]).unwrap(), events);
+    }
+
+    #[rstest]
+    #[case(
+        r#"{"a": [1}, "d": 2}"#,
+        "Unexpected char '}' at line 1, column 9, expected ']'"
+    )]
+    #[case(
+        r#"{"a": {"b": 1]}"#,
+        "Unexpected char ']' at line 1, column 14, expected '}'"
+    )]
+    #[case(
+        r#"{"a": }"#,
+        "Unexpected char '}' at line 1, column 7, expected a value"
+    )]
+    #[case(
+        r#"{"a": x}"#,
+        "Unexpected char 'x' at line 1, column 7, expected a value"
+    )]
+    #[case(r#"{"a": [1, 2"#, "Unexpected end of input at line 1, column 12")]
+    #[case(r#"{"a": "abc\"}"#, "Unexpected end of input at line 1, column 14")]
+    #[case(
+        r#"{"a": [] 1}"#,
+        "Unexpected char '1' at line 1, column 10, expected ',' or '}'"
+    )]
+    fn skip_errors(#[case] json_str: &str, #[case] message: &str) {
+        let error = read_skipping(json_str, &["a"]).unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+
+    #[test]
+    fn positions() {
+        let mut reader = JsonSliceReader::from_slice("{\n \"é\": [1,\n  x]}".as_bytes());
+        while reader.read_event().is_ok() {}
+        assert_eq!(
+            reader.position(),
+            TextPosition {
+                offset: 15,
+                line: 3,
+                column: 3
+            }
+        );
+
+        let mut reader = JsonSliceReader::from_slice("[[[[1]]]]".as_bytes());
+        reader.max_stack_size(2);
+        let error = loop {
+            match reader.read_event() {
+                Ok(JsonEvent::Eof) => panic!("the stack should overflow"),
+                Ok(_) => {}
+                Err(error) => break error,
+            }
+        };
+        assert!(matches!(
+            error,
+            JsonParseError::StackOverflow {
+                max_stack_size: 2,
+                ..
+            }
+        ));
+    }
+}
@@ -1,9 +1,9 @@
 #![allow(dead_code)]
 
+use crate::error::JsonParseError;
 use crate::event::JsonEvent;
-use anyhow::Result;
 use std::borrow::Borrow;
-use std::io::Write;
+use std::io::{self, Write};
 
 pub struct JsonWriter<W: Write> {
     writer: W,
@@ -14,7 +14,7 @@ impl<W: Write> JsonWriter<W> {
         Self { writer }
     }
 
-    pub fn write_event(&mut self, event: JsonEvent) -> Result<()> {
+    pub fn write_event(&mut self, event: &JsonEvent) -> Result<(), JsonParseError> {
         match event {
             JsonEvent::WhiteSpace(whitespace) => {
                 self.writer.write_all(whitespace.as_bytes())?;
@@ -27,7 +27,7 @@ impl<W: Write> JsonWriter<W> {
             }
             JsonEvent::Boolean(boolean) => {
                 self.writer
-                    .write_all(if boolean { b"true" } else { b"false" })?;
+                    .write_all(if *boolean { b"true" } else { b"false" })?;
             }
             JsonEvent::Null => {
                 self.writer.write_all(b"null")?;
@@ -60,7 +60,7 @@ impl<W: Write> JsonWriter<W> {
     }
 }
 
-fn write_escaped_json_string(s: &str, sink: &mut impl Write) -> Result<()> {
+fn write_escaped_json_string(s: &str, sink: &mut impl Write) -> io::Result<()> {
     sink.write_all(b"\"")?;
     let mut buffer = [b'\\', b'u', 0, 0, 0, 0];
     for c in s.chars() {
@@ -108,7 +108,7 @@ mod tests {
         let mut buffer = Vec::new();
         {
             let mut writer = JsonWriter::from_writer(&mut buffer);
-            for event in events {
+            for event in &events {
                 writer.write_event(event).unwrap();
             }
         }
@@ -132,7 +132,7 @@ mod tests {
     #[test]
     fn with_whitespace() {
         let events = vec![
-            JsonEvent::WhiteSpace("\n  ".to_string()),
+            JsonEvent::WhiteSpace(Cow::Borrowed("\n  ")),
             JsonEvent::StartObject,
             JsonEvent::ObjectKey(Cow::Owned("key".to_string())),
             JsonEvent::String(Cow::Owned("value".to_string())),
@@ -163,7 +163,7 @@ mod tests {
                 if event == JsonEvent::Eof {
                     break;
                 }
-                writer.write_event(event).unwrap();
+                writer.write_event(&event).unwrap();
             }
         }
         let output_json_str = String::from_utf8(output_json_buffer).unwrap();
@@ -191,7 +191,7 @@ mod tests {
                 if event == JsonEvent::Eof {
                     break;
                 }
-                writer.write_event(event).unwrap();
+                writer.write_event(&event).unwrap();
             }
         }
         let output_json_str = String::from_utf8(output_json_buffer).unwrap();
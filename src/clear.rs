@@ -0,0 +1,1092 @@
+use crate::events::{entries, entry_key, entry_value, Events};
+use crate::keys::KeyFilter;
+use crate::layout::{Layout, LAYOUTS};
+use crate::output::{MimeFilter, SizeLimit};
+use crate::path::{JsonPath, Segment};
+use crate::report::{CellCursor, ClearReport, ClearStats};
+use crate::rules::{Action, Change, ChangeKind, Indentation, PathPattern, RuleSet, RuleWriter};
+use crate::upgrade::upgrade;
+use crate::validate::{validate_notebook, Validator};
+use json_event_parser_witespace::{
+    JsonEvent, JsonParseError, JsonReader, JsonSliceReader, TextPosition,
+};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+pub const DEFAULT_KEEP_TAG: &str = "keep_output";
+
+/// What to clear from a notebook.
+#[derive(Debug, Clone)]
+pub struct ClearOptions {
+    /// Cells tagged with any of these in `metadata.tags` keep their outputs and execution count.
+    pub keep_tags: Vec<String>,
+    /// When active, outputs are filtered by MIME type instead of being removed altogether.
+    pub mime: MimeFilter,
+    /// When set, outputs no larger than the limit are kept instead of being removed.
+    pub size_limit: Option<SizeLimit>,
+    pub execution_count: ExecutionCount,
+    /// Notebook and cell metadata keys to strip.
+    pub keys: KeyFilter,
+    /// Rewrite nbformat 3 notebooks into the nbformat 4 layout before clearing them.
+    pub upgrade: bool,
+    /// Fail on notebooks without the structure of nbformat instead of rewriting them.
+    pub validate: bool,
+}
+
+/// What happens to the `execution_count` of cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionCount {
+    /// Reset to `null`, except in cells tagged to keep their outputs.
+    #[default]
+    Clear,
+    /// Leave every count as it is.
+    Keep,
+    /// Number the cells that keep outputs 1..N in document order, reset the others.
+    Renumber,
+}
+
+impl ClearOptions {
+    /// Whether outputs are filtered one by one rather than removed altogether.
+    fn filters_outputs(&self) -> bool {
+        self.mime.is_active() || self.size_limit.is_some()
+    }
+
+    fn filter_output(&self, output: Events, indentation: &Indentation) -> Option<Events> {
+        let output = if self.mime.is_active() {
+            self.mime.filter_output(output)?
+        } else {
+            output
+        };
+        match &self.size_limit {
+            Some(size_limit) => size_limit.limit_output(output, indentation),
+            None => Some(output),
+        }
+    }
+
+    /// Whether any output of the buffered `cell` is left after clearing.
+    fn keeps_outputs(&self, cell: &[JsonEvent<'static>], tagged: bool) -> bool {
+        entries(cell)
+            .into_iter()
+            .find(|entry| entry_key(entry) == Some("outputs"))
+            .is_some_and(|entry| {
+                let outputs = entries(entry_value(entry));
+                if tagged {
+                    !outputs.is_empty()
+                } else if self.filters_outputs() {
+                    outputs.into_iter().any(|output| {
+                        self.filter_output(output.to_vec(), &Indentation::default())
+                            .is_some()
+                    })
+                } else {
+                    false
+                }
+            })
+    }
+
+    /// The rules applying to every cell: outputs and execution counts are reset, or outputs
+    /// filtered one by one, and metadata keys stripped.
+    fn rules(&self) -> RuleSet {
+        let mut rules = RuleSet::default();
+        self.keys.add_rules(&mut rules);
+        let options = Rc::new(self.clone());
+        for layout in LAYOUTS {
+            if self.filters_outputs() {
+                let options = options.clone();
+                rules.push(
+                    layout.cell_pattern("outputs[*]"),
+                    Action::Edit(Rc::new(move |output, indentation| {
+                        options.filter_output(output, indentation)
+                    })),
+                );
+            } else {
+                rules.push(
+                    layout.cell_pattern("outputs"),
+                    Action::Replace(vec![JsonEvent::StartArray, JsonEvent::EndArray]),
+                );
+            }
+            if self.execution_count != ExecutionCount::Keep {
+                rules.push(
+                    layout.cell_pattern(layout.execution_count),
+                    Action::Replace(vec![JsonEvent::Null]),
+                );
+            }
+        }
+        rules
+    }
+
+    /// The rules taking precedence over [`Self::rules`] in a cell, given whether it is tagged to
+    /// keep its outputs and the count it is renumbered to.
+    fn cell_rules(&self, tagged: bool, count: Option<u64>) -> RuleSet {
+        let mut rules = RuleSet::default();
+        for layout in LAYOUTS {
+            let execution_count = layout.execution_count;
+            if tagged {
+                rules.push(layout.cell_pattern("outputs"), Action::Keep);
+                rules.push(layout.cell_pattern("outputs[*]"), Action::Keep);
+                if self.execution_count == ExecutionCount::Clear {
+                    rules.push(layout.cell_pattern(execution_count), Action::Keep);
+                }
+            }
+            if let Some(count) = count {
+                let count = vec![JsonEvent::Number(count.to_string().into())];
+                rules.push(
+                    layout.cell_pattern(execution_count),
+                    Action::Replace(count.clone()),
+                );
+                // Keep `execute_result` outputs in line with their renumbered cell.
+                rules.push(
+                    layout.cell_pattern(&format!("outputs[*].{}", execution_count)),
+                    Action::Replace(count),
+                );
+            }
+        }
+        rules
+    }
+}
+
+impl Default for ClearOptions {
+    fn default() -> Self {
+        Self {
+            keep_tags: vec![DEFAULT_KEEP_TAG.to_string()],
+            mime: MimeFilter::default(),
+            size_limit: None,
+            execution_count: ExecutionCount::Clear,
+            keys: KeyFilter::default(),
+            upgrade: false,
+            validate: true,
+        }
+    }
+}
+
+/// Clears the notebook read from `reader` into `writer`.
+///
+/// Each cell is buffered until its `metadata` has been read, since whether its outputs are kept
+/// depends on its tags and `execution_count` comes first in the usual key order. The rest of the
+/// cell, outputs included, is streamed. When renumbering, whole cells are buffered since their
+/// count depends on whether any of their outputs is kept. The values in outputs that are removed
+/// are skipped without being read, apart from their `output_type`.
+///
+/// Cells are found in both the nbformat 4 `cells` and the nbformat 3 `worksheets[*].cells`. When
+/// [`ClearOptions::upgrade`] is set, the whole notebook is read first to upgrade it if it is an
+/// nbformat 3 notebook.
+///
+/// Unless [`ClearOptions::validate`] is unset, a [`ValidationError`] is returned as soon as the
+/// notebook turns out not to have the structure of nbformat.
+pub fn clear_notebook<R: BufRead, W: Write>(
+    mut reader: R,
+    writer: W,
+    options: &ClearOptions,
+) -> anyhow::Result<ClearReport> {
+    if !options.upgrade {
+        return clear_events(
+            StreamReader {
+                reader: JsonReader::from_reader(reader),
+                buffer: vec![],
+            },
+            writer,
+            options,
+        );
+    }
+    let mut notebook = vec![];
+    reader.read_to_end(&mut notebook)?;
+    clear_notebook_slice(&notebook, writer, options)
+}
+
+/// Clears the notebook in memory `notebook` into `writer`, as [`clear_notebook`] does.
+///
+/// The notebook is read without being copied.
+pub fn clear_notebook_slice<W: Write>(
+    notebook: &[u8],
+    writer: W,
+    options: &ClearOptions,
+) -> anyhow::Result<ClearReport> {
+    let upgraded = if options.upgrade {
+        upgrade(notebook)?
+    } else {
+        None
+    };
+    let Some(upgraded) = upgraded else {
+        return clear_events(JsonSliceReader::from_slice(notebook), writer, options);
+    };
+    // Errors are reported at their position in the notebook read, not in the upgraded one.
+    if options.validate {
+        validate_notebook(notebook)?;
+    }
+    let options = ClearOptions {
+        validate: false,
+        ..options.clone()
+    };
+    let mut report = clear_events(JsonSliceReader::from_slice(&upgraded), writer, &options)?;
+    report.changed = true;
+    report.stats.bytes_before = notebook.len() as u64;
+    report.stats.bytes_saved = notebook.len() as i64 - report.stats.bytes_after as i64;
+    Ok(report)
+}
+
+/// Where the events of a notebook are read from.
+trait NotebookReader {
+    fn position(&self) -> TextPosition;
+
+    /// Reads the next event, borrowing the input read when it can.
+    fn read_event(&mut self) -> Result<JsonEvent<'_>, JsonParseError>;
+
+    /// Skips the next value without reading its events.
+    fn skip_value(&mut self) -> Result<(), JsonParseError>;
+}
+
+/// A notebook streamed from `R`.
+struct StreamReader<R> {
+    reader: JsonReader<R>,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> NotebookReader for StreamReader<R> {
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+
+    fn read_event(&mut self) -> Result<JsonEvent<'_>, JsonParseError> {
+        self.reader.read_event(&mut self.buffer)
+    }
+
+    fn skip_value(&mut self) -> Result<(), JsonParseError> {
+        self.reader.skip_value(&mut self.buffer)
+    }
+}
+
+impl NotebookReader for JsonSliceReader<'_> {
+    fn position(&self) -> TextPosition {
+        JsonSliceReader::position(self)
+    }
+
+    fn read_event(&mut self) -> Result<JsonEvent<'_>, JsonParseError> {
+        JsonSliceReader::read_event(self)
+    }
+
+    fn skip_value(&mut self) -> Result<(), JsonParseError> {
+        JsonSliceReader::skip_value(self)
+    }
+}
+
+fn clear_events<W: Write>(
+    mut reader: impl NotebookReader,
+    writer: W,
+    options: &ClearOptions,
+) -> anyhow::Result<ClearReport> {
+    let written = Cell::new(0);
+    let mut validator = options.validate.then(Validator::default);
+    let mut clearer = Clearer::new(
+        Counting {
+            inner: writer,
+            count: &written,
+        },
+        options,
+    );
+    // Follows the events as they are read, ahead of `clearer` while a cell is buffered.
+    let mut cursor = CellCursor::default();
+    let mut cell_events: Option<Events> = None;
+    // Whether the buffered cell is tagged to keep its outputs, known once its metadata is read.
+    let mut cell_tagged = None;
+    let no_rules = RuleSet::default();
+    let mut cell_rules = RuleSet::default();
+    // Whether the outputs of the cell being streamed are removed, so that their values can be
+    // skipped.
+    let mut removes_outputs = false;
+    let mut skipped = false;
+    let mut count = 0;
+
+    let read = loop {
+        let start = reader.position();
+        // The output of a skipped value is removed anyway, so `null` stands in for it.
+        let event = if std::mem::take(&mut skipped) {
+            JsonEvent::Null
+        } else {
+            reader.read_event()?
+        };
+
+        if event == JsonEvent::Eof {
+            if let Some(validator) = &validator {
+                validator.finish(start)?;
+            }
+            break start.offset;
+        }
+        if let Some(validator) = &mut validator {
+            validator.observe(&event, start)?;
+        }
+
+        let in_cell = cursor.in_cell();
+        cursor.observe(&event);
+        if !in_cell && cursor.in_cell() {
+            cell_events = Some(vec![]);
+            cell_tagged = None;
+        }
+
+        if let Some(events) = &mut cell_events {
+            // Only the events of a cell buffered until its metadata is read are copied.
+            events.push(event.into_owned());
+            // The tags are those of the current cell, which is over once the whole cell is read.
+            if cell_tagged.is_none() && cursor.cell_metadata_read() {
+                cell_tagged = Some(
+                    cursor
+                        .cell_tags()
+                        .iter()
+                        .any(|tag| options.keep_tags.contains(tag)),
+                );
+            }
+            let renumber = options.execution_count == ExecutionCount::Renumber;
+            if !cursor.in_cell() || (cursor.cell_metadata_read() && !renumber) {
+                let tagged = cell_tagged.unwrap_or(false);
+                let renumbered = renumber && options.keeps_outputs(events, tagged);
+                if renumbered {
+                    count += 1;
+                }
+                cell_rules = options.cell_rules(tagged, renumbered.then_some(count));
+                removes_outputs = !tagged && !options.filters_outputs();
+                for event in cell_events.take().into_iter().flatten() {
+                    clearer.handle(event, &cell_rules)?;
+                }
+            }
+        } else if cursor.in_cell() {
+            let is_key = matches!(event, JsonEvent::ObjectKey(_));
+            clearer.handle(event, &cell_rules)?;
+            skipped = removes_outputs && is_key && clearer.in_output_value();
+            if skipped {
+                reader.skip_value()?;
+            }
+        } else {
+            clearer.handle(event, &no_rules)?;
+        }
+    };
+
+    let mut report = clearer.finish()?;
+    report.stats.bytes_before = read;
+    report.stats.bytes_after = written.get();
+    report.stats.bytes_saved = read as i64 - written.get() as i64;
+    Ok(report)
+}
+
+struct Clearer<W: Write> {
+    writer: RuleWriter<W>,
+    rules: RuleSet,
+    cursor: CellCursor,
+    /// The path of the event in the notebook read, to know what the changed values were.
+    path: JsonPath,
+    patterns: StatsPatterns,
+    /// The `output_type` of the outputs of the cell at the given path.
+    output_types: (Vec<Segment>, Vec<Option<String>>),
+    /// Whether the `execution_count` read last was a number.
+    had_count: bool,
+    stats: ClearStats,
+}
+
+/// The paths of interest for the statistics, one pattern per cell layout. They are matched for
+/// every event, so are parsed once.
+struct StatsPatterns {
+    outputs: Vec<PathPattern>,
+    output: Vec<PathPattern>,
+    output_value: Vec<PathPattern>,
+    output_type: Vec<PathPattern>,
+    execution_count: Vec<PathPattern>,
+    /// The keys of the notebook and cell metadata, also matching the paths inside of them.
+    metadata_key: Vec<PathPattern>,
+}
+
+impl StatsPatterns {
+    fn new() -> Self {
+        let patterns = |path: fn(&Layout) -> String| {
+            LAYOUTS
+                .iter()
+                .map(|layout| layout.cell_pattern(&path(layout)))
+                .collect()
+        };
+        Self {
+            outputs: patterns(|_| "outputs".to_string()),
+            output: patterns(|_| "outputs[*]".to_string()),
+            output_value: patterns(|_| "outputs[*].*".to_string()),
+            output_type: patterns(|_| "outputs[*].output_type".to_string()),
+            execution_count: patterns(|layout| layout.execution_count.to_string()),
+            metadata_key: std::iter::once(
+                PathPattern::parse("metadata.*").expect("metadata paths are valid"),
+            )
+            .chain(patterns(|_| "metadata.*".to_string()))
+            .collect(),
+        }
+    }
+}
+
+fn any_matches(patterns: &[PathPattern], path: &[Segment]) -> bool {
+    patterns.iter().any(|pattern| pattern.matches(path))
+}
+
+/// Whether `path` is matched by any of `patterns`, or is inside of a value matched by them.
+fn any_matches_prefix(patterns: &[PathPattern], path: &[Segment]) -> bool {
+    patterns.iter().any(|pattern| {
+        let depth = pattern.segments().len();
+        path.len() >= depth && pattern.matches(&path[..depth])
+    })
+}
+
+impl<W: Write> Clearer<W> {
+    fn new(writer: W, options: &ClearOptions) -> Self {
+        Self {
+            writer: RuleWriter::new(writer),
+            rules: options.rules(),
+            cursor: CellCursor::default(),
+            path: JsonPath::default(),
+            patterns: StatsPatterns::new(),
+            output_types: (vec![], vec![]),
+            had_count: false,
+            stats: ClearStats::default(),
+        }
+    }
+
+    /// Handles the next event, with `cell_rules` taking precedence in the cell it belongs to.
+    fn handle(&mut self, event: JsonEvent<'_>, cell_rules: &RuleSet) -> anyhow::Result<()> {
+        self.cursor.observe(&event);
+        self.observe(&event);
+        self.writer.write_event(event, &[cell_rules, &self.rules])?;
+        let changes: Vec<_> = self.writer.drain_changes().collect();
+        for change in changes {
+            self.cursor.mark_changed();
+            self.count(change);
+        }
+        Ok(())
+    }
+
+    fn observe(&mut self, event: &JsonEvent) {
+        let path = self.path.segments();
+        match (event, path) {
+            (JsonEvent::String(output_type), [cell @ .., _, Segment::Index(output), _])
+                if any_matches(&self.patterns.output_type, path) =>
+            {
+                let (types_cell, types) = &mut self.output_types;
+                if types_cell != cell {
+                    *types_cell = cell.to_vec();
+                    types.clear();
+                }
+                if types.len() <= *output {
+                    types.resize(*output + 1, None);
+                }
+                types[*output] = Some(output_type.to_string());
+            }
+            (JsonEvent::Number(_) | JsonEvent::Null, _)
+                if any_matches(&self.patterns.execution_count, path) =>
+            {
+                self.had_count = matches!(event, JsonEvent::Number(_));
+            }
+            _ => {}
+        }
+        self.path.observe(event);
+    }
+
+    fn count(&mut self, change: Change) {
+        let path = change.path.as_slice();
+        let output_types = |cell: &[Segment]| match &self.output_types {
+            (types_cell, types) if types_cell == cell => types.as_slice(),
+            _ => &[],
+        };
+        let mut removed_outputs = vec![];
+        match change.kind {
+            ChangeKind::Removed if any_matches_prefix(&self.patterns.metadata_key, path) => {
+                self.stats.metadata_keys_removed += 1;
+            }
+            ChangeKind::Removed if any_matches(&self.patterns.output, path) => {
+                if let [cell @ .., _, Segment::Index(output)] = path {
+                    removed_outputs.push(output_types(cell).get(*output).cloned().flatten());
+                }
+            }
+            ChangeKind::Replaced(_) if any_matches(&self.patterns.outputs, path) => {
+                removed_outputs.extend(output_types(&path[..path.len() - 1]).iter().cloned());
+            }
+            ChangeKind::Replaced(value)
+                if any_matches(&self.patterns.execution_count, path)
+                    && self.had_count
+                    && value == [JsonEvent::Null] =>
+            {
+                self.stats.execution_counts_reset += 1;
+            }
+            _ => {}
+        }
+        for output_type in removed_outputs {
+            *self
+                .stats
+                .outputs_removed
+                .entry(output_type.unwrap_or_else(|| "unknown".to_string()))
+                .or_default() += 1;
+        }
+    }
+
+    /// Whether the value after the key just handled is in an output, other than its type.
+    fn in_output_value(&self) -> bool {
+        let path = self.path.segments();
+        any_matches(&self.patterns.output_value, path)
+            && !any_matches(&self.patterns.output_type, path)
+    }
+
+    /// Writes the whitespace trailing the notebook and returns the report.
+    fn finish(self) -> anyhow::Result<ClearReport> {
+        self.writer.finish()?;
+        let cells = self.cursor.cell_count();
+        let mut report = self.cursor.into_report();
+        report.stats = ClearStats {
+            cells,
+            ..self.stats
+        };
+        Ok(report)
+    }
+}
+
+/// Counts the bytes written through `inner`.
+struct Counting<'a, T> {
+    inner: T,
+    count: &'a Cell<u64>,
+}
+
+impl<T: Write> Write for Counting<'_, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count.set(self.count.get() + written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ChangedCell;
+    use std::fs;
+    use std::io::Cursor;
+
+    /// Clears `notebook`, which may be a fragment of a notebook since it is not validated, the
+    /// same whether it is streamed or in memory.
+    fn clear(notebook: &str, options: &ClearOptions) -> String {
+        let options = ClearOptions {
+            validate: false,
+            ..options.clone()
+        };
+        let mut output = vec![];
+        let report = clear_notebook(Cursor::new(notebook), &mut output, &options).unwrap();
+        let mut slice_output = vec![];
+        let slice_report =
+            clear_notebook_slice(notebook.as_bytes(), &mut slice_output, &options).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&slice_output),
+            String::from_utf8_lossy(&output)
+        );
+        assert_eq!(slice_report, report);
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn report_changed_cells() {
+        let notebook = fs::read("assets/notebook/sample.ipynb").unwrap();
+        let mut cleared = vec![];
+        let report = clear_notebook(
+            Cursor::new(&notebook),
+            &mut cleared,
+            &ClearOptions::default(),
+        )
+        .unwrap();
+        assert!(report.changed);
+        assert_eq!(
+            report.changed_cells,
+            vec![
+                ChangedCell {
+                    index: 0,
+                    id: Some("985e01e1".to_string())
+                },
+                ChangedCell {
+                    index: 1,
+                    id: Some("af6bbe72".to_string())
+                },
+            ]
+        );
+        assert_eq!(
+            report.stats,
+            ClearStats {
+                cells: 3,
+                outputs_removed: [("stream".to_string(), 2)].into(),
+                bytes_before: notebook.len() as u64,
+                bytes_after: cleared.len() as u64,
+                bytes_saved: notebook.len() as i64 - cleared.len() as i64,
+                execution_counts_reset: 2,
+                metadata_keys_removed: 0,
+            }
+        );
+
+        let mut slice_cleared = vec![];
+        let slice_report =
+            clear_notebook_slice(&notebook, &mut slice_cleared, &ClearOptions::default()).unwrap();
+        assert_eq!(slice_cleared, cleared);
+        assert_eq!(slice_report, report);
+
+        let report = clear_notebook(
+            Cursor::new(&cleared),
+            std::io::sink(),
+            &ClearOptions::default(),
+        )
+        .unwrap();
+        assert!(!report.changed);
+        assert!(report.changed_cells.is_empty());
+        assert_eq!(report.stats.bytes_saved, 0);
+    }
+
+    #[test]
+    fn keep_tagged_cells() {
+        let notebook = r#"{
+ "cells": [
+  {
+   "execution_count": 1,
+   "outputs": [{"output_type": "stream", "name": "stdout", "text": "1"}],
+   "metadata": {"tags": ["keep_output"]}
+  },
+  {
+   "execution_count": 2,
+   "metadata": {"tags": ["other"]},
+   "outputs": [{"output_type": "stream", "name": "stdout", "text": "2"}]
+  }
+ ]
+}"#;
+        let expected = r#"{
+ "cells": [
+  {
+   "execution_count": 1,
+   "outputs": [{"output_type": "stream", "name": "stdout", "text": "1"}],
+   "metadata": {"tags": ["keep_output"]}
+  },
+  {
+   "execution_count": null,
+   "metadata": {"tags": ["other"]},
+   "outputs": []
+  }
+ ]
+}"#;
+        assert_eq!(clear(notebook, &ClearOptions::default()), expected);
+
+        let options = ClearOptions {
+            keep_tags: vec!["other".to_string()],
+            ..ClearOptions::default()
+        };
+        let cleared = clear(notebook, &options);
+        assert!(cleared.contains(r#""text": "2""#));
+        assert!(!cleared.contains(r#""text": "1""#));
+    }
+
+    #[test]
+    fn filter_outputs_by_mime() {
+        let notebook = r#"{
+ "cells": [
+  {
+   "execution_count": 1,
+   "metadata": {},
+   "outputs": [
+    {
+     "data": {
+      "image/png": "iVBORw0KGgo=",
+      "text/plain": "<Figure>"
+     },
+     "metadata": {
+      "image/png": {"width": 10}
+     },
+     "output_type": "display_data"
+    },
+    {
+     "data": {
+      "text/html": "<b>1</b>"
+     },
+     "metadata": {},
+     "output_type": "display_data"
+    },
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": "1"
+    }
+   ]
+  }
+ ]
+}"#;
+        let expected = r#"{
+ "cells": [
+  {
+   "execution_count": null,
+   "metadata": {},
+   "outputs": [
+    {
+     "data": {
+      "text/plain": "<Figure>"
+     },
+     "metadata": {},
+     "output_type": "display_data"
+    },
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": "1"
+    }
+   ]
+  }
+ ]
+}"#;
+        let strip = ClearOptions {
+            mime: MimeFilter {
+                strip: vec!["image/*".to_string(), "text/html".to_string()],
+                keep: vec![],
+            },
+            ..ClearOptions::default()
+        };
+        assert_eq!(clear(notebook, &strip), expected);
+
+        let keep = ClearOptions {
+            mime: MimeFilter {
+                strip: vec![],
+                keep: vec!["text/plain".to_string()],
+            },
+            validate: false,
+            ..ClearOptions::default()
+        };
+        assert_eq!(clear(notebook, &keep), expected);
+
+        let report = clear_notebook(Cursor::new(notebook), std::io::sink(), &keep).unwrap();
+        assert_eq!(
+            report.stats.outputs_removed,
+            [("display_data".to_string(), 1)].into()
+        );
+    }
+
+    #[test]
+    fn remove_large_outputs() {
+        let notebook = r#"{
+ "cells": [
+  {
+   "execution_count": 1,
+   "metadata": {},
+   "outputs": [
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": "small"
+    },
+    {
+     "data": {
+      "image/png": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==",
+      "text/plain": "<Figure>"
+     },
+     "metadata": {},
+     "output_type": "display_data"
+    }
+   ]
+  }
+ ]
+}"#;
+        let small_output = r#"{
+     "name": "stdout",
+     "output_type": "stream",
+     "text": "small"
+    }"#;
+        let mut options = ClearOptions {
+            size_limit: Some(SizeLimit {
+                max_bytes: 100,
+                stub: false,
+            }),
+            ..ClearOptions::default()
+        };
+        let cleared = clear(notebook, &options);
+        assert!(cleared.contains(&format!("\"outputs\": [\n    {}\n   ]", small_output)));
+
+        options.size_limit = Some(SizeLimit {
+            max_bytes: 100,
+            stub: true,
+        });
+        let cleared = clear(notebook, &options);
+        assert!(cleared.contains(&format!(
+            r#"{},
+    {{
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "[output removed: 236 B image/png]\n"
+     ]
+    }}"#,
+            small_output
+        )));
+
+        // The stub follows the indentation of the outputs, not the layout of the removed one.
+        let image = "A".repeat(200);
+        let indented = format!(
+            "{{\n  \"cells\": [\n    {{\n      \"metadata\": {{}},\n      \"outputs\": [\n        {{\"data\": {{\"image/png\": \"{image}\"}}, \"metadata\": {{}}, \"output_type\": \"display_data\"}}\n      ]\n    }}\n  ]\n}}"
+        );
+        assert!(clear(&indented, &options).contains(
+            r#"      "outputs": [
+        {
+          "name": "stdout",
+          "output_type": "stream",
+          "text": [
+            "[output removed: 274 B image/png]\n"
+          ]
+        }
+      ]"#
+        ));
+        let compact = format!(
+            "{{\"cells\":[{{\"metadata\":{{}},\"outputs\":[{{\"data\":{{\"image/png\":\"{image}\"}},\"metadata\":{{}},\"output_type\":\"display_data\"}}]}}]}}"
+        );
+        assert!(clear(&compact, &options).contains(
+            r#""outputs":[{"name":"stdout","output_type":"stream","text":["[output removed: 268 B image/png]\n"]}]"#
+        ));
+    }
+
+    #[test]
+    fn keep_and_renumber_execution_counts() {
+        let notebook = r#"{
+ "cells": [
+  {"execution_count": 3, "metadata": {}, "outputs": [{"output_type": "stream", "text": "a"}]},
+  {"execution_count": 5, "metadata": {}, "outputs": [{"data": {"image/png": "x"}, "execution_count": 5, "metadata": {}, "output_type": "execute_result"}]},
+  {"execution_count": 7, "metadata": {"tags": ["keep_output"]}, "outputs": [{"data": {"text/plain": "1"}, "execution_count": 7, "metadata": {}, "output_type": "execute_result"}]},
+  {"execution_count": 8, "metadata": {}, "outputs": [{"output_type": "stream", "text": "b"}]}
+ ]
+}"#;
+        let keep = ClearOptions {
+            execution_count: ExecutionCount::Keep,
+            ..ClearOptions::default()
+        };
+        assert_eq!(
+            clear(notebook, &keep),
+            r#"{
+ "cells": [
+  {"execution_count": 3, "metadata": {}, "outputs": []},
+  {"execution_count": 5, "metadata": {}, "outputs": []},
+  {"execution_count": 7, "metadata": {"tags": ["keep_output"]}, "outputs": [{"data": {"text/plain": "1"}, "execution_count": 7, "metadata": {}, "output_type": "execute_result"}]},
+  {"execution_count": 8, "metadata": {}, "outputs": []}
+ ]
+}"#
+        );
+
+        let renumber = ClearOptions {
+            mime: MimeFilter {
+                strip: vec!["image/png".to_string()],
+                keep: vec![],
+            },
+            execution_count: ExecutionCount::Renumber,
+            ..ClearOptions::default()
+        };
+        assert_eq!(
+            clear(notebook, &renumber),
+            r#"{
+ "cells": [
+  {"execution_count": 1, "metadata": {}, "outputs": [{"output_type": "stream", "text": "a"}]},
+  {"execution_count": null, "metadata": {}, "outputs": []},
+  {"execution_count": 2, "metadata": {"tags": ["keep_output"]}, "outputs": [{"data": {"text/plain": "1"}, "execution_count": 2, "metadata": {}, "output_type": "execute_result"}]},
+  {"execution_count": 3, "metadata": {}, "outputs": [{"output_type": "stream", "text": "b"}]}
+ ]
+}"#
+        );
+
+        // Without a MIME filter, only the outputs of tagged cells are kept.
+        let renumber = ClearOptions {
+            execution_count: ExecutionCount::Renumber,
+            ..ClearOptions::default()
+        };
+        assert_eq!(
+            clear(notebook, &renumber),
+            r#"{
+ "cells": [
+  {"execution_count": null, "metadata": {}, "outputs": []},
+  {"execution_count": null, "metadata": {}, "outputs": []},
+  {"execution_count": 1, "metadata": {"tags": ["keep_output"]}, "outputs": [{"data": {"text/plain": "1"}, "execution_count": 1, "metadata": {}, "output_type": "execute_result"}]},
+  {"execution_count": null, "metadata": {}, "outputs": []}
+ ]
+}"#
+        );
+    }
+
+    #[test]
+    fn strip_metadata_keys() {
+        let notebook = r#"{
+ "cells": [
+  {
+   "execution_count": null,
+   "metadata": {
+    "ExecuteTime": {"end_time": "2023-01-01T00:00:00"},
+    "tags": [],
+    "vscode": {"languageId": "python"},
+    "collapsed": true
+   },
+   "outputs": []
+  }
+ ],
+ "metadata": {
+  "language_info": {"name": "python", "version": "3.11.0"},
+  "widgets": {"state": {}}
+ }
+}"#;
+        let options = ClearOptions {
+            keys: KeyFilter::new(
+                &[
+                    "cell.metadata.vscode".to_string(),
+                    "metadata.language_info.version".to_string(),
+                ],
+                &["metadata.widgets".to_string()],
+            )
+            .unwrap(),
+            validate: false,
+            ..ClearOptions::default()
+        };
+        let mut output = vec![];
+        let report = clear_notebook(Cursor::new(notebook), &mut output, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            r#"{
+ "cells": [
+  {
+   "execution_count": null,
+   "metadata": {
+    "tags": []
+   },
+   "outputs": []
+  }
+ ],
+ "metadata": {
+  "language_info": {"name": "python"},
+  "widgets": {"state": {}}
+ }
+}"#
+        );
+        assert_eq!(
+            report.changed_cells,
+            vec![ChangedCell { index: 0, id: None }]
+        );
+    }
+
+    #[test]
+    fn clear_by_path_only() {
+        let notebook = r#"{
+ "cells": [
+  {
+   "execution_count": 1,
+   "metadata": {
+    "extension": {"outputs": [1], "collapsed": true, "execution_count": 2}
+   },
+   "outputs": [{"output_type": "stream", "text": "1"}]
+  }
+ ],
+ "metadata": {"collapsed": true, "outputs": []}
+}"#;
+        assert_eq!(
+            clear(notebook, &ClearOptions::default()),
+            r#"{
+ "cells": [
+  {
+   "execution_count": null,
+   "metadata": {
+    "extension": {"outputs": [1], "collapsed": true, "execution_count": 2}
+   },
+   "outputs": []
+  }
+ ],
+ "metadata": {"collapsed": true, "outputs": []}
+}"#
+        );
+    }
+
+    #[test]
+    fn clear_v3_notebooks() {
+        let notebook = r#"{
+ "metadata": {"name": ""},
+ "nbformat": 3,
+ "nbformat_minor": 0,
+ "worksheets": [
+  {
+   "cells": [
+    {
+     "cell_type": "code",
+     "collapsed": false,
+     "input": ["print(1)"],
+     "language": "python",
+     "metadata": {"tags": ["keep_output"], "collapsed": true},
+     "outputs": [{"output_type": "stream", "stream": "stdout", "text": ["1"]}],
+     "prompt_number": 1
+    },
+    {
+     "cell_type": "code",
+     "input": ["2"],
+     "language": "python",
+     "metadata": {},
+     "outputs": [{"output_type": "pyout", "prompt_number": 2, "text": ["2"]}],
+     "prompt_number": 2
+    }
+   ],
+   "metadata": {}
+  }
+ ]
+}"#;
+        let mut cleared = vec![];
+        let report =
+            clear_notebook(notebook.as_bytes(), &mut cleared, &ClearOptions::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(cleared).unwrap(),
+            r#"{
+ "metadata": {"name": ""},
+ "nbformat": 3,
+ "nbformat_minor": 0,
+ "worksheets": [
+  {
+   "cells": [
+    {
+     "cell_type": "code",
+     "collapsed": false,
+     "input": ["print(1)"],
+     "language": "python",
+     "metadata": {"tags": ["keep_output"]},
+     "outputs": [{"output_type": "stream", "stream": "stdout", "text": ["1"]}],
+     "prompt_number": 1
+    },
+    {
+     "cell_type": "code",
+     "input": ["2"],
+     "language": "python",
+     "metadata": {},
+     "outputs": [],
+     "prompt_number": null
+    }
+   ],
+   "metadata": {}
+  }
+ ]
+}"#
+        );
+        assert_eq!(report.stats.cells, 2);
+        assert_eq!(
+            report.stats.outputs_removed,
+            [("pyout".to_string(), 1)].into()
+        );
+        assert_eq!(report.stats.execution_counts_reset, 1);
+        assert_eq!(report.stats.metadata_keys_removed, 1);
+        assert_eq!(
+            report.changed_cells,
+            vec![
+                ChangedCell { index: 0, id: None },
+                ChangedCell { index: 1, id: None }
+            ]
+        );
+
+        let options = ClearOptions {
+            upgrade: true,
+            ..ClearOptions::default()
+        };
+        let upgraded = clear(notebook, &options);
+        assert!(upgraded.contains(r#""nbformat": 4"#));
+        assert!(upgraded.contains(r#""nbformat_minor": 5"#));
+        assert!(upgraded.contains(r#""id": "cell-1""#));
+        assert!(upgraded.contains(r#""execution_count": 1"#));
+        assert!(upgraded.contains(r#""execution_count": null"#));
+        assert!(upgraded.contains(r#""name": "stdout""#));
+        assert!(!upgraded.contains("worksheets"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Project configuration, read from `.nbclo.toml` or the `[tool.nbclo]` table of `pyproject.toml`.
+
+use crate::clear::{ClearOptions, ExecutionCount, DEFAULT_KEEP_TAG};
+use crate::keys::KeyFilter;
+use crate::output::{MimeFilter, SizeLimit};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = ".nbclo.toml";
+pub const PYPROJECT_FILE: &str = "pyproject.toml";
+
+/// Clearing settings; unset values fall back to the next configuration layer or the defaults.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub keep_tags: Option<Vec<String>>,
+    pub strip_mime: Option<Vec<String>>,
+    pub keep_mime: Option<Vec<String>>,
+    pub max_output_bytes: Option<usize>,
+    pub stub_removed_outputs: Option<bool>,
+    pub execution_count: Option<ExecutionCount>,
+    pub strip_keys: Option<Vec<String>>,
+    pub keep_keys: Option<Vec<String>>,
+    /// Rewrite nbformat 3 notebooks into the nbformat 4 layout.
+    pub upgrade: Option<bool>,
+    /// Check that notebooks have the structure of nbformat before rewriting them.
+    pub validate: Option<bool>,
+    /// Glob patterns, relative to the configuration file, of notebooks to leave alone.
+    pub exclude: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Layers `overrides` on top of `self`.
+    pub fn merge(self, overrides: Config) -> Config {
+        Config {
+            keep_tags: overrides.keep_tags.or(self.keep_tags),
+            strip_mime: overrides.strip_mime.or(self.strip_mime),
+            keep_mime: overrides.keep_mime.or(self.keep_mime),
+            max_output_bytes: overrides.max_output_bytes.or(self.max_output_bytes),
+            stub_removed_outputs: overrides.stub_removed_outputs.or(self.stub_removed_outputs),
+            execution_count: overrides.execution_count.or(self.execution_count),
+            strip_keys: overrides.strip_keys.or(self.strip_keys),
+            keep_keys: overrides.keep_keys.or(self.keep_keys),
+            upgrade: overrides.upgrade.or(self.upgrade),
+            validate: overrides.validate.or(self.validate),
+            exclude: overrides.exclude.or(self.exclude),
+        }
+    }
+
+    /// Fills every unset value with its default.
+    pub fn resolved(self) -> Config {
+        self.merge(Config::default()).fill_defaults()
+    }
+
+    fn fill_defaults(self) -> Config {
+        Config {
+            keep_tags: self
+                .keep_tags
+                .or_else(|| Some(vec![DEFAULT_KEEP_TAG.to_string()])),
+            strip_mime: self.strip_mime.or_else(|| Some(vec![])),
+            keep_mime: self.keep_mime.or_else(|| Some(vec![])),
+            max_output_bytes: self.max_output_bytes,
+            stub_removed_outputs: self.stub_removed_outputs.or(Some(false)),
+            execution_count: self.execution_count.or(Some(ExecutionCount::Clear)),
+            strip_keys: self.strip_keys.or_else(|| Some(vec![])),
+            keep_keys: self.keep_keys.or_else(|| Some(vec![])),
+            upgrade: self.upgrade.or(Some(false)),
+            validate: self.validate.or(Some(true)),
+            exclude: self.exclude.or_else(|| Some(vec![])),
+        }
+    }
+
+    pub fn options(&self) -> anyhow::Result<ClearOptions> {
+        let config = self.clone().resolved();
+        Ok(ClearOptions {
+            keep_tags: config.keep_tags.unwrap_or_default(),
+            mime: MimeFilter {
+                strip: config.strip_mime.unwrap_or_default(),
+                keep: config.keep_mime.unwrap_or_default(),
+            },
+            size_limit: config.max_output_bytes.map(|max_bytes| SizeLimit {
+                max_bytes,
+                stub: config.stub_removed_outputs.unwrap_or_default(),
+            }),
+            execution_count: config.execution_count.unwrap_or_default(),
+            keys: KeyFilter::new(
+                &config.strip_keys.unwrap_or_default(),
+                &config.keep_keys.unwrap_or_default(),
+            )?,
+            upgrade: config.upgrade.unwrap_or_default(),
+            validate: config.validate.unwrap_or(true),
+        })
+    }
+}
+
+/// A configuration file and the settings read from it.
+#[derive(Debug, Clone)]
+pub struct ProjectConfig {
+    pub path: PathBuf,
+    pub config: Config,
+}
+
+impl ProjectConfig {
+    /// Reads `.nbclo.toml`, or the `[tool.nbclo]` table of `pyproject.toml`, in `dir`.
+    fn read(dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = dir.join(CONFIG_FILE);
+        if path.is_file() {
+            let config = toml::from_str(&fs::read_to_string(&path)?)
+                .with_context(|| format!("invalid configuration in {}", path.display()))?;
+            return Ok(Some(Self { path, config }));
+        }
+
+        #[derive(Deserialize)]
+        struct PyProject {
+            tool: Option<Tool>,
+        }
+        #[derive(Deserialize)]
+        struct Tool {
+            nbclo: Option<Config>,
+        }
+
+        let path = dir.join(PYPROJECT_FILE);
+        if path.is_file() {
+            let pyproject: PyProject = toml::from_str(&fs::read_to_string(&path)?)
+                .with_context(|| format!("invalid configuration in {}", path.display()))?;
+            if let Some(config) = pyproject.tool.and_then(|tool| tool.nbclo) {
+                return Ok(Some(Self { path, config }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether `path` matches one of the `exclude` patterns, or is inside a matching directory.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        let Some(exclude) = &self.config.exclude else {
+            return false;
+        };
+        let (Some(dir), Ok(path)) = (self.path.parent(), fs::canonicalize(path)) else {
+            return false;
+        };
+        let Ok(relative) = path.strip_prefix(dir) else {
+            return false;
+        };
+        exclude
+            .iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .any(|pattern| {
+                relative
+                    .ancestors()
+                    .any(|ancestor| pattern.matches_path(ancestor))
+            })
+    }
+}
+
+/// Finds the configuration of each notebook by walking up from its directory, reading each
+/// directory at most once.
+#[derive(Debug, Default)]
+pub struct ConfigLoader {
+    cache: HashMap<PathBuf, Option<ProjectConfig>>,
+}
+
+impl ConfigLoader {
+    /// The configuration applying to `path`, a notebook or `-` for stdin.
+    pub fn for_path(&mut self, path: &Path) -> anyhow::Result<Option<ProjectConfig>> {
+        let dir = match path.parent() {
+            Some(parent) if path.as_os_str() != "-" && !parent.as_os_str().is_empty() => {
+                fs::canonicalize(parent)?
+            }
+            _ => std::env::current_dir()?,
+        };
+        self.for_dir(&dir)
+    }
+
+    fn for_dir(&mut self, dir: &Path) -> anyhow::Result<Option<ProjectConfig>> {
+        if let Some(config) = self.cache.get(dir) {
+            return Ok(config.clone());
+        }
+        let config = match ProjectConfig::read(dir)? {
+            Some(config) => Some(config),
+            None => match dir.parent() {
+                Some(parent) => self.for_dir(parent)?,
+                None => None,
+            },
+        };
+        self.cache.insert(dir.to_path_buf(), config.clone());
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discover_and_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("project/notebooks/scratch")).unwrap();
+        fs::create_dir_all(root.join("project/other")).unwrap();
+        fs::write(
+            root.join("project/pyproject.toml"),
+            "[project]\nname = \"example\"\n\n[tool.nbclo]\nkeep-tags = [\"results\"]\nmax-output-bytes = 1000\nexecution-count = \"renumber\"\nexclude = [\"notebooks/scratch\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("project/other/.nbclo.toml"),
+            "strip-mime = [\"image/*\"]\n",
+        )
+        .unwrap();
+
+        let mut loader = ConfigLoader::default();
+        let project = loader
+            .for_path(&root.join("project/notebooks/a.ipynb"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(project.path, root.join("project/pyproject.toml"));
+        assert_eq!(project.config.keep_tags, Some(vec!["results".to_string()]));
+        assert_eq!(
+            project.config.execution_count,
+            Some(ExecutionCount::Renumber)
+        );
+
+        let notebook = root.join("project/notebooks/scratch/b.ipynb");
+        fs::write(&notebook, "{}").unwrap();
+        assert!(project.is_excluded(&notebook));
+        assert!(!project.is_excluded(&root.join("project/notebooks")));
+
+        let other = loader
+            .for_path(&root.join("project/other/c.ipynb"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(other.path, root.join("project/other/.nbclo.toml"));
+        assert_eq!(other.config.max_output_bytes, None);
+
+        let cli = Config {
+            max_output_bytes: Some(10),
+            ..Config::default()
+        };
+        let merged = project.config.merge(cli);
+        assert_eq!(merged.max_output_bytes, Some(10));
+        assert_eq!(merged.keep_tags, Some(vec!["results".to_string()]));
+    }
+
+    #[test]
+    fn reject_unknown_keys() {
+        assert!(toml::from_str::<Config>("keep-tag = [\"a\"]").is_err());
+    }
+}
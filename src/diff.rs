@@ -0,0 +1,68 @@
+//! Unified diffs between a notebook and its cleared version.
+
+use similar::{ChangeTag, TextDiff};
+use std::fmt::Write;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+
+/// A unified diff from `original` to `cleared`, with ANSI colors if `color` is set.
+///
+/// Returns an empty string if both are the same.
+pub fn unified_diff(original: &str, cleared: &str, name: &str, color: bool) -> String {
+    if original == cleared {
+        return String::new();
+    }
+    let paint = |style: &'static str| if color { style } else { "" };
+    let reset = paint(RESET);
+
+    let diff = TextDiff::from_lines(original, cleared);
+    let mut unified = diff.unified_diff();
+    unified.context_radius(3);
+
+    let mut output = String::new();
+    let bold = paint(BOLD);
+    writeln!(output, "{bold}--- a/{name}{reset}").unwrap();
+    writeln!(output, "{bold}+++ b/{name}{reset}").unwrap();
+    for hunk in unified.iter_hunks() {
+        writeln!(output, "{}{}{}", paint(CYAN), hunk.header(), reset).unwrap();
+        for change in hunk.iter_changes() {
+            let (sign, style) = match change.tag() {
+                ChangeTag::Delete => ('-', paint(RED)),
+                ChangeTag::Insert => ('+', paint(GREEN)),
+                ChangeTag::Equal => (' ', ""),
+            };
+            let line = change.value();
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            let reset = if style.is_empty() { "" } else { reset };
+            writeln!(output, "{style}{sign}{line}{reset}").unwrap();
+            if change.missing_newline() {
+                output.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lines() {
+        let original = "{\n \"a\": 1,\n \"b\": [\n  2\n ]\n}\n";
+        let cleared = "{\n \"a\": 1,\n \"b\": []\n}\n";
+        assert_eq!(
+            unified_diff(original, cleared, "x.ipynb", false),
+            "--- a/x.ipynb\n+++ b/x.ipynb\n@@ -1,6 +1,4 @@\n {\n  \"a\": 1,\n- \"b\": [\n-  2\n- ]\n+ \"b\": []\n }\n"
+        );
+        assert_eq!(unified_diff(original, original, "x.ipynb", false), "");
+
+        let colored = unified_diff(original, cleared, "x.ipynb", true);
+        assert!(colored.contains("\x1b[31m- \"b\": [\x1b[0m\n"));
+        assert!(colored.contains("\x1b[32m+ \"b\": []\x1b[0m\n"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Editing of buffered JSON values as event lists, keeping their original whitespace.
+
+use json_event_parser_witespace::{JsonEvent, JsonWriter};
+use std::io::{self, Write};
+use std::ops::Range;
+
+pub type Events = Vec<JsonEvent<'static>>;
+
+/// Rebuilds the object or array `value` with each entry replaced by the result of `f`.
+///
+/// Entries are given to `f` without their surrounding whitespace; an object entry starts with its
+/// `ObjectKey` event. Returning `None` removes the entry together with its separating comma.
+/// Whitespace in front of kept entries is preserved, so is the whitespace before the closing
+/// bracket unless every entry is removed.
+pub fn map_entries(value: Events, mut f: impl FnMut(Events) -> Option<Events>) -> Events {
+    let (Some(open), Some(close)) = (value.first().cloned(), value.last().cloned()) else {
+        return value;
+    };
+    let separator = match open {
+        JsonEvent::StartObject => JsonEvent::NextObjectValue,
+        JsonEvent::StartArray => JsonEvent::NextArrayValue,
+        _ => return value,
+    };
+    let entries = split_entries(&value);
+    if entries.is_empty() {
+        return value;
+    }
+
+    let last = entries.len() - 1;
+    let mut output = vec![open];
+    let mut written = 0;
+    for (i, range) in entries.into_iter().enumerate() {
+        let (before, content, after) = trim_whitespace(&value[range]);
+        match f(content.to_vec()) {
+            Some(content) => {
+                if written > 0 {
+                    output.push(separator.clone());
+                }
+                output.extend_from_slice(before);
+                output.extend(content);
+                output.extend_from_slice(after);
+                written += 1;
+            }
+            None if i == last && written > 0 => output.extend_from_slice(after),
+            None => {}
+        }
+    }
+    output.push(close);
+    output
+}
+
+/// The entries of the object or array `value`, without their surrounding whitespace.
+pub fn entries<'a, 'e>(value: &'a [JsonEvent<'e>]) -> Vec<&'a [JsonEvent<'e>]> {
+    split_entries(value)
+        .into_iter()
+        .map(|range| trim_whitespace(&value[range]).1)
+        .collect()
+}
+
+/// The value of the object entry `entry`, as given by [`entries`].
+pub fn entry_value<'a, 'e>(entry: &'a [JsonEvent<'e>]) -> &'a [JsonEvent<'e>] {
+    let start = entry
+        .iter()
+        .skip(1)
+        .take_while(|e| is_whitespace(e))
+        .count()
+        + 1;
+    &entry[start.min(entry.len())..]
+}
+
+/// Replaces the value of the object entry `entry`, as given by [`map_entries`].
+pub fn map_entry_value(mut entry: Events, f: impl FnOnce(Events) -> Events) -> Events {
+    let start = entry.len() - entry_value(&entry).len();
+    let value = entry.split_off(start);
+    entry.extend(f(value));
+    entry
+}
+
+/// The key of the object entry `entry`, as given by [`map_entries`].
+pub fn entry_key<'a>(entry: &'a [JsonEvent]) -> Option<&'a str> {
+    match entry.first() {
+        Some(JsonEvent::ObjectKey(key)) => Some(key),
+        _ => None,
+    }
+}
+
+/// The number of bytes `events` take once written.
+pub fn serialized_len(events: &[JsonEvent]) -> usize {
+    struct Counter(usize);
+
+    impl Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    let mut writer = JsonWriter::from_writer(&mut counter);
+    for event in events {
+        writer
+            .write_event(event)
+            .expect("writing to a counter does not fail");
+    }
+    counter.0
+}
+
+/// The ranges of the entries of the object or array `value`, without the separating commas.
+fn split_entries(value: &[JsonEvent]) -> Vec<Range<usize>> {
+    let inner = 1..value.len().saturating_sub(1);
+    if value[inner.clone()].iter().all(is_whitespace) {
+        return vec![];
+    }
+
+    let mut entries = vec![];
+    let mut depth = 0;
+    let mut start = inner.start;
+    for (i, event) in value.iter().enumerate().take(inner.end).skip(inner.start) {
+        match event {
+            JsonEvent::StartObject | JsonEvent::StartArray => depth += 1,
+            JsonEvent::EndObject | JsonEvent::EndArray => depth -= 1,
+            JsonEvent::NextObjectValue | JsonEvent::NextArrayValue if depth == 0 => {
+                entries.push(start..i);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(start..inner.end);
+    entries
+}
+
+/// Splits `events` into its leading whitespace, its content and its trailing whitespace.
+fn trim_whitespace<'a, 'e>(
+    events: &'a [JsonEvent<'e>],
+) -> (
+    &'a [JsonEvent<'e>],
+    &'a [JsonEvent<'e>],
+    &'a [JsonEvent<'e>],
+) {
+    let leading = events.iter().take_while(|e| is_whitespace(e)).count();
+    let trailing = events[leading..]
+        .iter()
+        .rev()
+        .take_while(|e| is_whitespace(e))
+        .count();
+    let (before, rest) = events.split_at(leading);
+    let (content, after) = rest.split_at(rest.len() - trailing);
+    (before, content, after)
+}
+
+fn is_whitespace(event: &JsonEvent) -> bool {
+    matches!(event, JsonEvent::WhiteSpace(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use json_event_parser_witespace::{JsonReader, JsonWriter};
+
+    fn parse(json: &str) -> Events {
+        let mut reader = JsonReader::from_reader(json.as_bytes());
+        let mut buffer = vec![];
+        let mut events = vec![];
+        loop {
+            match reader.read_event(&mut buffer).unwrap().into_owned() {
+                JsonEvent::Eof => return events,
+                event => events.push(event),
+            }
+        }
+    }
+
+    fn write(events: Events) -> String {
+        let mut output = vec![];
+        let mut writer = JsonWriter::from_writer(&mut output);
+        for event in &events {
+            writer.write_event(event).unwrap();
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    fn remove_keys(json: &str, keys: &[&str]) -> String {
+        write(map_entries(parse(json), |entry| {
+            (!keys.contains(&entry_key(&entry).unwrap())).then_some(entry)
+        }))
+    }
+
+    #[test]
+    fn remove_object_entries() {
+        let json = "{\n \"a\": 1,\n \"b\": [1, 2],\n \"c\": {\"d\": 3}\n}";
+        assert_eq!(remove_keys(json, &[]), json);
+        assert_eq!(
+            remove_keys(json, &["a"]),
+            "{\n \"b\": [1, 2],\n \"c\": {\"d\": 3}\n}"
+        );
+        assert_eq!(
+            remove_keys(json, &["b"]),
+            "{\n \"a\": 1,\n \"c\": {\"d\": 3}\n}"
+        );
+        assert_eq!(
+            remove_keys(json, &["c"]),
+            "{\n \"a\": 1,\n \"b\": [1, 2]\n}"
+        );
+        assert_eq!(remove_keys(json, &["a", "b", "c"]), "{}");
+        assert_eq!(remove_keys("{ }", &["a"]), "{ }");
+    }
+
+    #[test]
+    fn map_array_elements_and_values() {
+        let json = "[\n {\"a\": 1},\n {\"a\": 2}\n]";
+        let mapped = map_entries(parse(json), |entry| {
+            Some(map_entries(entry, |entry| {
+                Some(map_entry_value(entry, |_| vec![JsonEvent::Null]))
+            }))
+        });
+        assert_eq!(write(mapped), "[\n {\"a\": null},\n {\"a\": null}\n]");
+
+        let mut index = 0;
+        let filtered = map_entries(parse(json), |entry| {
+            index += 1;
+            (index == 2).then_some(entry)
+        });
+        assert_eq!(write(filtered), "[\n {\"a\": 2}\n]");
+    }
+}
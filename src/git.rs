@@ -0,0 +1,225 @@
+use anyhow::{anyhow, Context};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FILTER_SECTION: &str = "[filter \"nbclo\"]";
+const FILTER_CONFIG: &str = "\tclean = nbclo filter %f\n\tsmudge = cat\n";
+const ATTRIBUTES_LINE: &str = "*.ipynb filter=nbclo";
+
+/// The files of a git repository that the clean filter is registered in.
+#[derive(Debug)]
+pub struct Repository {
+    config: PathBuf,
+    attributes: PathBuf,
+}
+
+/// Whether each half of the filter registration is present.
+#[derive(Debug, PartialEq)]
+pub struct FilterStatus {
+    pub config: bool,
+    pub attributes: bool,
+}
+
+impl Repository {
+    /// Finds the repository containing `dir` by walking up to the nearest `.git`.
+    pub fn discover(dir: &Path) -> anyhow::Result<Self> {
+        let dir = fs::canonicalize(dir)?;
+        let root = dir
+            .ancestors()
+            .find(|ancestor| ancestor.join(".git").exists())
+            .ok_or_else(|| anyhow!("{} is not in a git repository", dir.display()))?;
+
+        let mut git_dir = root.join(".git");
+        if git_dir.is_file() {
+            // Worktrees and submodules point to their git directory with a `gitdir:` line.
+            let content = fs::read_to_string(&git_dir)?;
+            let target = content
+                .strip_prefix("gitdir:")
+                .ok_or_else(|| anyhow!("{} is not a valid .git file", git_dir.display()))?;
+            git_dir = root.join(target.trim());
+        }
+        // The config of a linked worktree lives in the main repository.
+        if let Ok(common_dir) = fs::read_to_string(git_dir.join("commondir")) {
+            git_dir = git_dir.join(common_dir.trim());
+        }
+
+        Ok(Self {
+            config: git_dir.join("config"),
+            attributes: root.join(".gitattributes"),
+        })
+    }
+
+    pub fn install(&self) -> anyhow::Result<()> {
+        let config = remove_section(&read_or_empty(&self.config)?);
+        write_appended(
+            &self.config,
+            &config,
+            &format!("{FILTER_SECTION}\n{FILTER_CONFIG}"),
+        )?;
+
+        let attributes = read_or_empty(&self.attributes)?;
+        if !attributes
+            .lines()
+            .any(|line| line.trim() == ATTRIBUTES_LINE)
+        {
+            write_appended(
+                &self.attributes,
+                &attributes,
+                &format!("{ATTRIBUTES_LINE}\n"),
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn uninstall(&self) -> anyhow::Result<()> {
+        let config = read_or_empty(&self.config)?;
+        let removed = remove_section(&config);
+        if removed != config {
+            write(&self.config, &removed)?;
+        }
+
+        let attributes = read_or_empty(&self.attributes)?;
+        if attributes
+            .lines()
+            .any(|line| line.trim() == ATTRIBUTES_LINE)
+        {
+            let removed = attributes
+                .lines()
+                .filter(|line| line.trim() != ATTRIBUTES_LINE)
+                .map(|line| format!("{line}\n"))
+                .collect::<String>();
+            // A file left empty was created by `install`.
+            if removed.trim().is_empty() {
+                fs::remove_file(&self.attributes)
+                    .with_context(|| format!("failed to remove {}", self.attributes.display()))?;
+            } else {
+                write(&self.attributes, &removed)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> anyhow::Result<FilterStatus> {
+        Ok(FilterStatus {
+            config: read_or_empty(&self.config)?
+                .lines()
+                .any(|line| line.trim() == FILTER_SECTION),
+            attributes: read_or_empty(&self.attributes)?
+                .lines()
+                .any(|line| line.trim() == ATTRIBUTES_LINE),
+        })
+    }
+
+    pub fn config_path(&self) -> &Path {
+        &self.config
+    }
+
+    pub fn attributes_path(&self) -> &Path {
+        &self.attributes
+    }
+}
+
+fn read_or_empty(path: &Path) -> anyhow::Result<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+fn write(path: &Path, content: &str) -> anyhow::Result<()> {
+    fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn write_appended(path: &Path, content: &str, appended: &str) -> anyhow::Result<()> {
+    let separator = if content.is_empty() || content.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    write(path, &format!("{content}{separator}{appended}"))
+}
+
+/// Removes the `[filter "nbclo"]` section, up to the next section header, from a git config.
+fn remove_section(config: &str) -> String {
+    let mut in_section = false;
+    let mut output = String::with_capacity(config.len());
+    for line in config.lines() {
+        if line.trim_start().starts_with('[') {
+            in_section = line.trim() == FILTER_SECTION;
+        }
+        if !in_section {
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_and_uninstall() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("notebooks")).unwrap();
+        let config = "[core]\n\tbare = false\n[remote \"origin\"]\n\turl = ../origin\n";
+        fs::write(root.join(".git/config"), config).unwrap();
+        fs::write(root.join(".gitattributes"), "*.png binary").unwrap();
+
+        let repository = Repository::discover(&root.join("notebooks")).unwrap();
+        assert_eq!(
+            repository.status().unwrap(),
+            FilterStatus {
+                config: false,
+                attributes: false
+            }
+        );
+
+        repository.install().unwrap();
+        repository.install().unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join(".git/config")).unwrap(),
+            format!("{config}[filter \"nbclo\"]\n\tclean = nbclo filter %f\n\tsmudge = cat\n")
+        );
+        assert_eq!(
+            fs::read_to_string(root.join(".gitattributes")).unwrap(),
+            "*.png binary\n*.ipynb filter=nbclo\n"
+        );
+        assert_eq!(
+            repository.status().unwrap(),
+            FilterStatus {
+                config: true,
+                attributes: true
+            }
+        );
+
+        repository.uninstall().unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join(".git/config")).unwrap(),
+            config
+        );
+        assert_eq!(
+            fs::read_to_string(root.join(".gitattributes")).unwrap(),
+            "*.png binary\n"
+        );
+
+        fs::remove_file(root.join(".gitattributes")).unwrap();
+        repository.install().unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join(".gitattributes")).unwrap(),
+            "*.ipynb filter=nbclo\n"
+        );
+        repository.uninstall().unwrap();
+        assert!(!root.join(".gitattributes").exists());
+    }
+
+    #[test]
+    fn outside_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Repository::discover(dir.path()).is_err());
+    }
+}
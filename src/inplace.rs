@@ -0,0 +1,104 @@
+use crate::clear::{clear_notebook, clear_notebook_slice, ClearOptions};
+use crate::input::map_file;
+use crate::report::ClearReport;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Rewrites the notebook at `path` without ever truncating it.
+///
+/// The cleared notebook is streamed into a temporary file next to the original, which is
+/// renamed over it only once the whole output has been written. On any error the original is
+/// left untouched. Permissions are copied from the original; the modification time is that of
+/// the rewrite, as with any other edit.
+pub fn clear_inplace(path: &Path, options: &ClearOptions) -> anyhow::Result<ClearReport> {
+    // Write through symlinks instead of replacing them with a regular file.
+    let path = fs::canonicalize(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let permissions = fs::metadata(&path)?.permissions();
+
+    let file = File::open(&path)?;
+    let mut temp = tempfile::Builder::new()
+        .prefix(".nbclo")
+        .suffix(".tmp")
+        .tempfile_in(dir)?;
+    let report = {
+        let mut writer = BufWriter::new(temp.as_file_mut());
+        // The map is dropped before the notebook is replaced.
+        let report = match map_file(&file)? {
+            Some(notebook) => clear_notebook_slice(&notebook, &mut writer, options)?,
+            None => clear_notebook(BufReader::new(file), &mut writer, options)?,
+        };
+        writer.flush()?;
+        report
+    };
+    temp.as_file().sync_all()?;
+    fs::set_permissions(temp.path(), permissions)?;
+    temp.persist(&path)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notebook_with_cells(count: usize) -> String {
+        let cell = r#"  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "metadata": {},
+   "outputs": [
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "Hello World\n"
+     ]
+    }
+   ],
+   "source": [
+    "print(\"Hello World\")"
+   ]
+  }"#;
+        format!(
+            "{{\n \"cells\": [\n{}\n ],\n \"metadata\": {{}},\n \"nbformat\": 4,\n \"nbformat_minor\": 5\n}}\n",
+            vec![cell; count].join(",\n")
+        )
+    }
+
+    #[test]
+    fn inplace_larger_than_read_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.ipynb");
+        let notebook = notebook_with_cells(1000);
+        assert!(notebook.len() > 64 * 1024);
+        fs::write(&path, &notebook).unwrap();
+
+        let options = ClearOptions::default();
+        clear_inplace(&path, &options).unwrap();
+
+        let mut expected = vec![];
+        clear_notebook(
+            std::io::Cursor::new(notebook.as_bytes()),
+            &mut expected,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn inplace_keeps_original_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.ipynb");
+        let notebook = notebook_with_cells(10);
+        let broken = &notebook[..notebook.len() / 2];
+        fs::write(&path, broken).unwrap();
+
+        assert!(clear_inplace(&path, &ClearOptions::default()).is_err());
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), broken);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
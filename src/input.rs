@@ -0,0 +1,120 @@
+use memmap2::Mmap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+const NOTEBOOK_EXTENSION: &str = "ipynb";
+const CHECKPOINTS_DIR: &str = ".ipynb_checkpoints";
+
+/// Expands the paths given on the command line into the list of notebooks to process.
+///
+/// Files are taken as they are, directories are walked recursively for `*.ipynb` files
+/// (skipping `.ipynb_checkpoints`), and paths that do not exist are expanded as glob patterns.
+/// Anything that cannot be resolved is passed through so that it is reported as a failure when
+/// it is opened.
+pub fn collect_notebooks(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut notebooks = vec![];
+    for path in paths {
+        if path.exists() {
+            collect_path(path, &mut notebooks)?;
+            continue;
+        }
+
+        let matches = match path.to_str() {
+            Some(pattern) if is_glob(pattern) => glob::glob(pattern)?
+                .filter_map(Result::ok)
+                .collect::<Vec<_>>(),
+            _ => vec![],
+        };
+        if matches.is_empty() {
+            notebooks.push(path.clone());
+        }
+        for path in matches {
+            collect_path(&path, &mut notebooks)?;
+        }
+    }
+    Ok(notebooks)
+}
+
+/// Maps `file` in memory to read it without copying it, if it is a regular file.
+///
+/// Returns `None` for pipes and other special files, and for empty files which can not be
+/// mapped, which are to be read as streams instead.
+pub fn map_file(file: &File) -> io::Result<Option<Mmap>> {
+    let metadata = file.metadata()?;
+    if !metadata.is_file() || metadata.len() == 0 {
+        return Ok(None);
+    }
+    // SAFETY: the map is only read, but the file being modified by another process while it is
+    // mapped is undefined behavior. Like other tools mapping the files they read, nbclo assumes
+    // notebooks are not written while they are being cleared.
+    unsafe { Mmap::map(file) }.map(Some)
+}
+
+fn collect_path(path: &Path, notebooks: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if path.is_dir() {
+        walk_dir(path, notebooks)
+    } else {
+        notebooks.push(path.to_path_buf());
+        Ok(())
+    }
+}
+
+fn walk_dir(dir: &Path, notebooks: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name == CHECKPOINTS_DIR) {
+                continue;
+            }
+            walk_dir(&path, notebooks)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == NOTEBOOK_EXTENSION)
+        {
+            notebooks.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_directories_and_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sub/.ipynb_checkpoints")).unwrap();
+        for file in [
+            "a.ipynb",
+            "b.txt",
+            "sub/c.ipynb",
+            "sub/.ipynb_checkpoints/c-checkpoint.ipynb",
+        ] {
+            fs::write(root.join(file), "{}").unwrap();
+        }
+
+        let notebooks = collect_notebooks(&[root.to_path_buf()]).unwrap();
+        assert_eq!(
+            notebooks,
+            vec![root.join("a.ipynb"), root.join("sub/c.ipynb")]
+        );
+
+        let notebooks =
+            collect_notebooks(&[root.join("*.txt"), root.join("missing.ipynb")]).unwrap();
+        assert_eq!(
+            notebooks,
+            vec![root.join("b.txt"), root.join("missing.ipynb")]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::layout::LAYOUTS;
+use crate::rules::{Action, PathPattern, PatternSegment, RuleSet};
+use anyhow::bail;
+
+/// Metadata keys stripped unless kept with `--keep-key`.
+pub const DEFAULT_STRIP_KEYS: &[&str] = &[
+    "metadata.widgets",
+    "metadata.papermill",
+    "metadata.colab",
+    "metadata.vscode",
+    "cell.metadata.collapsed",
+    "cell.metadata.scrolled",
+    "cell.metadata.ExecuteTime",
+    "cell.metadata.execution",
+    "cell.metadata.papermill",
+    "cell.metadata.colab",
+    "cell.metadata.vscode",
+];
+
+const NOTEBOOK_METADATA: &str = "metadata";
+const CELL_METADATA: &str = "cell.metadata";
+
+/// A dotted key path such as `cell.metadata.execution`, where `*` matches any single key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeyPattern(Vec<String>);
+
+impl KeyPattern {
+    fn parse(pattern: &str) -> anyhow::Result<Self> {
+        let in_metadata = [NOTEBOOK_METADATA, CELL_METADATA].iter().any(|prefix| {
+            pattern
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('.') && rest.len() > 1)
+        });
+        if !in_metadata {
+            bail!(
+                "invalid key path `{}`, expected `{}.<key>` or `{}.<key>`",
+                pattern,
+                NOTEBOOK_METADATA,
+                CELL_METADATA
+            );
+        }
+        Ok(Self(pattern.split('.').map(str::to_string).collect()))
+    }
+
+    /// The patterns of the matching paths in the notebook, one per cell layout for cell keys.
+    fn path_patterns(&self) -> Vec<PathPattern> {
+        let segments = |keys: &[String]| {
+            keys.iter()
+                .map(|key| match key.as_str() {
+                    "*" => PatternSegment::AnyKey,
+                    key => PatternSegment::Key(key.to_string()),
+                })
+                .collect::<Vec<_>>()
+        };
+        match self.0.split_first() {
+            Some((cell, keys)) if cell == "cell" => LAYOUTS
+                .iter()
+                .map(|layout| layout.cell_segments(segments(keys)))
+                .collect(),
+            _ => vec![PathPattern::new(segments(&self.0))],
+        }
+    }
+}
+
+/// Selects the notebook and cell metadata keys to strip.
+#[derive(Debug, Clone)]
+pub struct KeyFilter {
+    strip: Vec<KeyPattern>,
+    keep: Vec<KeyPattern>,
+}
+
+impl Default for KeyFilter {
+    fn default() -> Self {
+        Self::new(&[], &[]).expect("default key paths are valid")
+    }
+}
+
+impl KeyFilter {
+    /// Strips the default keys and `strip`, except for the keys matching `keep`.
+    pub fn new(strip: &[String], keep: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            strip: DEFAULT_STRIP_KEYS
+                .iter()
+                .copied()
+                .chain(strip.iter().map(String::as_str))
+                .map(KeyPattern::parse)
+                .collect::<anyhow::Result<_>>()?,
+            keep: keep
+                .iter()
+                .map(|pattern| KeyPattern::parse(pattern))
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
+    /// Adds the rules removing the keys to strip. A kept key is left in place, though keys to
+    /// strip inside of it are still removed.
+    pub fn add_rules(&self, rules: &mut RuleSet) {
+        for pattern in self.keep.iter().flat_map(KeyPattern::path_patterns) {
+            rules.push(pattern, Action::Keep);
+        }
+        for pattern in self.strip.iter().flat_map(KeyPattern::path_patterns) {
+            rules.push(pattern, Action::Remove);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::Segment;
+
+    #[test]
+    fn parse_patterns() {
+        assert!(KeyPattern::parse("metadata.widgets").is_ok());
+        assert!(KeyPattern::parse("cell.metadata.*").is_ok());
+        assert!(KeyPattern::parse("metadata").is_err());
+        assert!(KeyPattern::parse("cell.outputs").is_err());
+        assert!(KeyPattern::parse("metadata_x.y").is_err());
+    }
+
+    #[test]
+    fn path_patterns() {
+        let path_patterns = |pattern| KeyPattern::parse(pattern).unwrap().path_patterns();
+        let parse = |pattern| PathPattern::parse(pattern).unwrap();
+        assert_eq!(
+            path_patterns("metadata.*.version"),
+            vec![parse("metadata.*.version")]
+        );
+        assert_eq!(
+            path_patterns("cell.metadata.collapsed"),
+            vec![
+                parse("cells[*].metadata.collapsed"),
+                parse("worksheets[*].cells[*].metadata.collapsed")
+            ]
+        );
+    }
+
+    #[test]
+    fn default_rules() {
+        let mut rules = RuleSet::default();
+        KeyFilter::default().add_rules(&mut rules);
+        let path = |path: &str| {
+            path.split('.')
+                .map(|segment| match segment.parse() {
+                    Ok(index) => Segment::Index(index),
+                    Err(_) => Segment::Key(segment.to_string()),
+                })
+                .collect::<Vec<_>>()
+        };
+        for key in ["widgets", "papermill", "colab", "vscode"] {
+            let path = path(&format!("metadata.{key}"));
+            assert!(matches!(rules.action(&path), Some(Action::Remove)), "{key}");
+        }
+        for key in ["collapsed", "ExecuteTime", "colab", "vscode"] {
+            for cell in ["cells.0", "worksheets.0.cells.0"] {
+                let path = path(&format!("{cell}.metadata.{key}"));
+                assert!(matches!(rules.action(&path), Some(Action::Remove)), "{key}");
+            }
+        }
+        assert!(rules.action(&path("metadata.kernelspec")).is_none());
+        assert!(rules.action(&path("cells.0.metadata.tags")).is_none());
+    }
+}
@@ -0,0 +1,96 @@
+//! Where cells are in the notebook formats.
+
+use crate::path::Segment;
+use crate::rules::{PathPattern, PatternSegment};
+use std::sync::LazyLock;
+
+/// Where the cells of a notebook are, and the key of their execution count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// The pattern of the cells, such as `cells[*]`.
+    pub cells: &'static str,
+    /// The key of the execution count of cells, also used in `execute_result` outputs.
+    pub execution_count: &'static str,
+    /// The position in [`LAYOUTS`].
+    index: usize,
+}
+
+/// nbformat 4, then nbformat 3 where cells are in worksheets and counts are prompt numbers.
+///
+/// The cells of both are at different paths, so the rules of both apply to every notebook.
+pub const LAYOUTS: [Layout; 2] = [
+    Layout {
+        cells: "cells[*]",
+        execution_count: "execution_count",
+        index: 0,
+    },
+    Layout {
+        cells: "worksheets[*].cells[*]",
+        execution_count: "prompt_number",
+        index: 1,
+    },
+];
+
+/// The parsed `cells` of [`LAYOUTS`], which are looked up for every event.
+static CELLS_PATTERNS: LazyLock<[PathPattern; 2]> = LazyLock::new(|| {
+    LAYOUTS.map(|layout| PathPattern::parse(layout.cells).expect("cell paths are valid"))
+});
+
+impl Layout {
+    /// The pattern of the cells.
+    pub fn cells_pattern(&self) -> &'static PathPattern {
+        &CELLS_PATTERNS[self.index]
+    }
+
+    /// The pattern of `path` inside of a cell.
+    pub fn cell_pattern(&self, path: &str) -> PathPattern {
+        PathPattern::parse(&format!("{}.{}", self.cells, path)).expect("cell paths are valid")
+    }
+
+    /// The pattern of the cell `segments`, inside of a cell.
+    pub fn cell_segments(&self, segments: impl IntoIterator<Item = PatternSegment>) -> PathPattern {
+        let mut pattern = self.cells_pattern().segments().to_vec();
+        pattern.extend(segments);
+        PathPattern::new(pattern)
+    }
+
+    /// The number of segments in the path of a cell.
+    pub fn cell_depth(&self) -> usize {
+        self.cells_pattern().segments().len()
+    }
+
+    /// Splits `path` into the path of the cell it is in and the path inside of that cell.
+    pub fn split_cell_path<'a>(
+        &self,
+        path: &'a [Segment],
+    ) -> Option<(&'a [Segment], &'a [Segment])> {
+        let cells = self.cells_pattern();
+        let depth = cells.segments().len();
+        (path.len() >= depth && cells.matches(&path[..depth])).then(|| path.split_at(depth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_cell_paths() {
+        let key = |key: &str| Segment::Key(key.to_string());
+        let path = [
+            key("worksheets"),
+            Segment::Index(0),
+            key("cells"),
+            Segment::Index(2),
+            key("outputs"),
+        ];
+        assert_eq!(LAYOUTS[0].split_cell_path(&path), None);
+        assert_eq!(
+            LAYOUTS[1].split_cell_path(&path),
+            Some((&path[..4], &path[4..]))
+        );
+        assert!(LAYOUTS[1]
+            .cell_pattern("prompt_number")
+            .matches(&[&path[..4], &[key("prompt_number")]].concat()));
+    }
+}
@@ -0,0 +1,107 @@
+//! Clearing of Jupyter notebook outputs, streaming the notebook JSON while keeping its layout.
+//!
+//! ```
+//! use fast_notebook_clear_output::{clear_notebook, ClearOptions};
+//!
+//! let notebook = r#"{"cells": [{"cell_type": "code", "execution_count": 1, "metadata": {}, "outputs": [{"output_type": "stream", "text": "1"}], "source": "print(1)"}], "metadata": {}, "nbformat": 4, "nbformat_minor": 5}"#;
+//! let mut cleared = vec![];
+//! let report = clear_notebook(notebook.as_bytes(), &mut cleared, &ClearOptions::default())?;
+//! assert!(report.changed);
+//! assert_eq!(
+//!     String::from_utf8(cleared)?,
+//!     r#"{"cells": [{"cell_type": "code", "execution_count": null, "metadata": {}, "outputs": [], "source": "print(1)"}], "metadata": {}, "nbformat": 4, "nbformat_minor": 5}"#
+//! );
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod clear;
+pub mod config;
+pub mod diff;
+pub mod events;
+pub mod git;
+pub mod inplace;
+pub mod input;
+pub mod keys;
+pub mod layout;
+pub mod output;
+pub mod path;
+pub mod pool;
+pub mod report;
+pub mod rules;
+pub mod upgrade;
+pub mod validate;
+
+pub use clear::{
+    clear_notebook, clear_notebook_slice, ClearOptions, ExecutionCount, DEFAULT_KEEP_TAG,
+};
+pub use inplace::clear_inplace;
+pub use keys::KeyFilter;
+pub use output::{MimeFilter, SizeLimit};
+pub use report::{ChangedCell, ClearReport};
+pub use validate::ValidationError;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(notebook: &str, options: &ClearOptions) -> (String, ClearReport) {
+        let mut output = vec![];
+        let report = clear_notebook(notebook.as_bytes(), &mut output, options).unwrap();
+        (String::from_utf8(output).unwrap(), report)
+    }
+
+    #[test]
+    fn clear_in_memory_notebooks() {
+        let notebook = r#"{
+ "cells": [
+  {"cell_type": "markdown", "metadata": {}, "source": ["Title"]},
+  {"cell_type": "code", "execution_count": 2, "id": "a1", "metadata": {"collapsed": false}, "outputs": [{"output_type": "stream", "name": "stdout", "text": "1"}], "source": ["print(1)"]}
+ ],
+ "metadata": {},
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"#;
+        let (cleared, report) = clear(notebook, &ClearOptions::default());
+        assert_eq!(
+            cleared,
+            r#"{
+ "cells": [
+  {"cell_type": "markdown", "metadata": {}, "source": ["Title"]},
+  {"cell_type": "code", "execution_count": null, "id": "a1", "metadata": {}, "outputs": [], "source": ["print(1)"]}
+ ],
+ "metadata": {},
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"#
+        );
+        assert_eq!(
+            report.changed_cells,
+            vec![ChangedCell {
+                index: 1,
+                id: Some("a1".to_string())
+            }]
+        );
+
+        assert_eq!(report.stats.cells, 2);
+        assert_eq!(report.stats.execution_counts_reset, 1);
+        assert_eq!(report.stats.metadata_keys_removed, 1);
+
+        let (recleared, report) = clear(&cleared, &ClearOptions::default());
+        assert_eq!(recleared, cleared);
+        assert!(!report.changed);
+        assert_eq!(report.stats.outputs_removed.len(), 0);
+    }
+
+    #[test]
+    fn reject_invalid_json() {
+        let mut output = vec![];
+        assert!(clear_notebook(
+            r#"{"cells": [}"#.as_bytes(),
+            &mut output,
+            &ClearOptions::default()
+        )
+        .is_err());
+    }
+}
@@ -1,146 +1,610 @@
-use clap::Parser;
-use json_event_parser_witespace::{JsonEvent, JsonReader, JsonWriter};
+use clap::{Parser, Subcommand};
+use fast_notebook_clear_output::config::{Config, ConfigLoader};
+use fast_notebook_clear_output::git::Repository;
+use fast_notebook_clear_output::report::ClearStats;
+use fast_notebook_clear_output::{
+    clear_inplace, clear_notebook, clear_notebook_slice, diff, input, pool, ClearOptions,
+    ClearReport, ExecutionCount,
+};
+use serde::Serialize;
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    #[arg(value_enum)]
-    output: Output,
-    input_file: PathBuf,
+    #[command(subcommand)]
+    command: Command,
 }
 
-#[derive(clap::ValueEnum, Debug, Clone)]
-enum Output {
-    Inplace,
-    Stdout,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Replace the notebooks with their cleared version.
+    Inplace(Inputs),
+    /// Write the cleared notebooks to stdout.
+    Stdout(Inputs),
+    /// Report the cells that would be cleared and fail if there are any, without writing.
+    Check(Inputs),
+    /// Clear a notebook read from stdin to stdout, for use as a git clean filter.
+    Filter {
+        #[command(flatten)]
+        clear: ClearArgs,
+        /// Path of the notebook in the repository, `%f` in the filter command, to read its
+        /// configuration from. Notebooks it excludes are passed through unchanged.
+        path: Option<PathBuf>,
+    },
+    /// Register `nbclo filter` as the git clean filter for notebooks of the current repository.
+    Install,
+    /// Remove the clean filter registered by `install`.
+    Uninstall,
+    /// Show whether the clean filter is registered in the current repository.
+    Status,
+    /// Inspect the configuration read from `.nbclo.toml` or `pyproject.toml`.
+    #[command(subcommand)]
+    Config(ConfigCommand),
 }
 
-#[derive(Debug, Clone, Copy)]
-enum State {
-    Root,
-    Cells,
-    CellsArrayStart,
-    Outputs,
-    OutputsArrayStart,
-    OutputsArrayEnd,
-    ExecutionCount,
-    MetaData,
-    Collapsed,
-}
-
-fn flush_events<'a>(
-    skip: bool,
-    writer: &mut JsonWriter<Box<dyn Write>>,
-    spaces: &mut Vec<String>,
-    event: &JsonEvent<'a>,
-) -> anyhow::Result<()> {
-    if !skip {
-        for space in std::mem::take(spaces) {
-            writer.write_event(JsonEvent::WhiteSpace(space))?;
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the configuration applying to a notebook, merged with the given options.
+    Show {
+        #[command(flatten)]
+        clear: ClearArgs,
+        /// Notebook or directory to resolve the configuration for. Defaults to the current
+        /// directory.
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct Inputs {
+    #[command(flatten)]
+    clear: ClearArgs,
+    /// Notebooks to clear. Directories are searched recursively for `*.ipynb` files, and `-`
+    /// reads a notebook from stdin.
+    #[arg(required = true)]
+    input_files: Vec<PathBuf>,
+    /// Number of notebooks to clear in parallel. Defaults to the number of CPUs.
+    #[arg(short = 'j', long, value_name = "N")]
+    jobs: Option<usize>,
+    /// Print a unified diff of what clearing changes instead of writing anything. Colors are
+    /// used when stdout is a terminal.
+    #[arg(long)]
+    diff: bool,
+    /// Write a JSON report of what was removed from each notebook to this file.
+    #[arg(long, value_name = "PATH")]
+    report: Option<PathBuf>,
+}
+
+/// The contents of the `--report` file.
+#[derive(Serialize, Debug)]
+struct Report {
+    files: Vec<FileReport>,
+}
+
+/// An entry of the `--report` file.
+#[derive(Serialize, Debug)]
+struct FileReport {
+    path: PathBuf,
+    #[serde(flatten)]
+    stats: Option<ClearStats>,
+    elapsed_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ClearArgs {
+    /// Keep the outputs and execution count of cells with this tag in `metadata.tags`.
+    /// Defaults to `keep_output`.
+    #[arg(long = "keep-tag", value_name = "TAG")]
+    keep_tags: Vec<String>,
+    /// Remove this MIME type, or family such as `image/*`, from outputs instead of removing
+    /// whole outputs. Outputs left without data are removed.
+    #[arg(long = "strip-mime", value_name = "MIME")]
+    strip_mime: Vec<String>,
+    /// Keep only these MIME types in outputs instead of removing whole outputs.
+    #[arg(long = "keep-mime", value_name = "MIME")]
+    keep_mime: Vec<String>,
+    /// Keep outputs up to this serialized size in bytes and remove only larger ones.
+    #[arg(long, value_name = "N")]
+    max_output_bytes: Option<usize>,
+    /// Replace outputs removed by `--max-output-bytes` with a note of what was removed.
+    #[arg(
+        long,
+        requires = "max_output_bytes",
+        overrides_with = "no_stub_removed_outputs"
+    )]
+    stub_removed_outputs: bool,
+    /// Remove outputs larger than `--max-output-bytes` without a note, the default.
+    #[arg(long, overrides_with = "stub_removed_outputs")]
+    no_stub_removed_outputs: bool,
+    /// Keep the execution count of every cell.
+    #[arg(long, overrides_with_all = ["renumber", "clear_count"])]
+    keep_count: bool,
+    /// Number the cells that keep outputs 1..N in document order and reset the others.
+    #[arg(long, overrides_with_all = ["keep_count", "clear_count"])]
+    renumber: bool,
+    /// Reset execution counts to `null` except in cells keeping their outputs, the default.
+    #[arg(long, overrides_with_all = ["keep_count", "renumber"])]
+    clear_count: bool,
+    /// Also strip this metadata key path, such as `cell.metadata.vscode` or
+    /// `metadata.language_info.version`. `*` matches any single key.
+    #[arg(long = "strip-key", value_name = "PATH")]
+    strip_keys: Vec<String>,
+    /// Keep this metadata key path even if it is stripped by default or by `--strip-key`.
+    #[arg(long = "keep-key", value_name = "PATH")]
+    keep_keys: Vec<String>,
+    /// Rewrite nbformat 3 notebooks into the nbformat 4 layout, as Jupyter would when saving
+    /// them. Other notebooks are left in their layout.
+    #[arg(long, overrides_with = "no_upgrade")]
+    upgrade: bool,
+    /// Clear nbformat 3 notebooks in their own layout, the default.
+    #[arg(long, overrides_with = "upgrade")]
+    no_upgrade: bool,
+    /// Check that notebooks have the structure of an nbformat notebook before rewriting them,
+    /// the default.
+    #[arg(long, overrides_with = "no_validate")]
+    validate: bool,
+    /// Rewrite any JSON file without checking that it has the structure of an nbformat notebook.
+    #[arg(long, overrides_with = "validate")]
+    no_validate: bool,
+}
+
+impl ClearArgs {
+    /// The options given on the command line, overriding those of the configuration file.
+    fn config(self) -> Config {
+        let non_empty = |values: Vec<String>| (!values.is_empty()).then_some(values);
+        // Only the last of a flag and its opposite is set.
+        let flag = |on: bool, off: bool| (on || off).then_some(on);
+        Config {
+            keep_tags: non_empty(self.keep_tags),
+            strip_mime: non_empty(self.strip_mime),
+            keep_mime: non_empty(self.keep_mime),
+            max_output_bytes: self.max_output_bytes,
+            stub_removed_outputs: flag(self.stub_removed_outputs, self.no_stub_removed_outputs),
+            execution_count: if self.keep_count {
+                Some(ExecutionCount::Keep)
+            } else if self.renumber {
+                Some(ExecutionCount::Renumber)
+            } else if self.clear_count {
+                Some(ExecutionCount::Clear)
+            } else {
+                None
+            },
+            strip_keys: non_empty(self.strip_keys),
+            keep_keys: non_empty(self.keep_keys),
+            upgrade: flag(self.upgrade, self.no_upgrade),
+            validate: flag(self.validate, self.no_validate),
+            exclude: None,
         }
-        writer.write_event(event.to_owned())?;
+    }
+}
+
+/// Clears the notebook at `path` read from `input` into `output`, with the configuration
+/// applying to `path`, writing nothing unless the whole notebook is cleared, so that git never
+/// stores part of a notebook.
+fn filter<R: Read, W: Write>(
+    mut input: R,
+    mut output: W,
+    path: &Path,
+    overrides: Config,
+) -> anyhow::Result<()> {
+    let project = ConfigLoader::default().for_path(path)?;
+    let mut notebook = vec![];
+    input.read_to_end(&mut notebook)?;
+    if project
+        .as_ref()
+        .is_some_and(|project| project.is_excluded(path))
+    {
+        output.write_all(&notebook)?;
     } else {
-        spaces.clear();
+        let options = project
+            .map(|project| project.config)
+            .unwrap_or_default()
+            .merge(overrides)
+            .options()?;
+        let mut cleared = Vec::with_capacity(notebook.len());
+        clear_notebook_slice(&notebook, &mut cleared, &options)?;
+        output.write_all(&cleared)?;
     }
+    output.flush()?;
     Ok(())
 }
 
-fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+#[derive(Debug, Clone, Copy)]
+enum Output {
+    Inplace,
+    Stdout,
+    Check,
+}
 
-    let file_reader = BufReader::new(File::open(&args.input_file)?);
-    let mut json_reader: JsonReader<BufReader<File>> = JsonReader::from_reader(file_reader);
-    let mut buffer = Vec::new();
+fn main() -> anyhow::Result<ExitCode> {
+    let args = Args::parse();
 
-    let mut writer: JsonWriter<Box<dyn Write>> = JsonWriter::from_writer(match args.output {
-        Output::Inplace => Box::new(BufWriter::new(File::create(&args.input_file)?)),
-        Output::Stdout => Box::new(BufWriter::new(std::io::stdout())),
-    });
+    match args.command {
+        Command::Inplace(inputs) => clear_files(Output::Inplace, inputs),
+        Command::Stdout(inputs) => clear_files(Output::Stdout, inputs),
+        Command::Check(inputs) => clear_files(Output::Check, inputs),
+        Command::Filter { clear, path } => {
+            filter(
+                std::io::stdin().lock(),
+                std::io::stdout().lock(),
+                path.as_deref().unwrap_or(Path::new("-")),
+                clear.config(),
+            )?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Install => {
+            let repository = Repository::discover(&std::env::current_dir()?)?;
+            repository.install()?;
+            println!(
+                "nbclo filter installed in {} and {}",
+                repository.config_path().display(),
+                repository.attributes_path().display()
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Uninstall => {
+            let repository = Repository::discover(&std::env::current_dir()?)?;
+            repository.uninstall()?;
+            println!(
+                "nbclo filter removed from {} and {}",
+                repository.config_path().display(),
+                repository.attributes_path().display()
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Status => {
+            let repository = Repository::discover(&std::env::current_dir()?)?;
+            let status = repository.status()?;
+            let installed = |installed| {
+                if installed {
+                    "installed"
+                } else {
+                    "not installed"
+                }
+            };
+            println!(
+                "{}: filter {}",
+                repository.config_path().display(),
+                installed(status.config)
+            );
+            println!(
+                "{}: attributes {}",
+                repository.attributes_path().display(),
+                installed(status.attributes)
+            );
+            Ok(if status.config && status.attributes {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        Command::Config(ConfigCommand::Show { clear, path }) => {
+            let path = path.unwrap_or_else(|| PathBuf::from("."));
+            // A directory is resolved as if it held the notebook.
+            let notebook = if path.is_dir() {
+                path.join("notebook.ipynb")
+            } else {
+                path
+            };
+            let project = ConfigLoader::default().for_path(&notebook)?;
+            match &project {
+                Some(project) => println!("# {}", project.path.display()),
+                None => println!("# no configuration file found"),
+            }
+            let config = project
+                .map(|project| project.config)
+                .unwrap_or_default()
+                .merge(clear.config());
+            // Check the configuration the same way as when clearing.
+            config.options()?;
+            print!("{}", toml::to_string(&config.resolved())?);
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
 
-    let mut state = State::Root;
-    let mut stack = 0;
-    let mut skip = false;
-    let mut save_spaces = vec![];
+/// A notebook to clear, with the options resolved from its configuration.
+struct Job {
+    notebook: PathBuf,
+    options: anyhow::Result<ClearOptions>,
+}
 
-    loop {
-        let event: JsonEvent = json_reader.read_event(&mut buffer)?.to_owned();
+/// The outcome of clearing a notebook, with what is printed to stdout for it.
+struct Processed {
+    result: anyhow::Result<ClearReport>,
+    stdout: Vec<u8>,
+    elapsed: Duration,
+}
 
-        if event == JsonEvent::Eof {
-            break;
-        }
+fn clear_files(output: Output, inputs: Inputs) -> anyhow::Result<ExitCode> {
+    let notebooks = input::collect_notebooks(&inputs.input_files)?;
+    let overrides = inputs.clear.config();
+    let mut loader = ConfigLoader::default();
+    let mut jobs = vec![];
+    for notebook in notebooks {
+        let options = match loader.for_path(&notebook) {
+            Ok(Some(project)) if project.is_excluded(&notebook) => continue,
+            Ok(project) => project
+                .map(|project| project.config)
+                .unwrap_or_default()
+                .merge(overrides.clone())
+                .options(),
+            Err(err) => Err(err),
+        };
+        jobs.push(Job { notebook, options });
+    }
 
-        match (state, &event) {
-            (State::OutputsArrayStart, JsonEvent::EndArray) => {
-                stack -= 1;
-                if stack == 0 {
-                    state = State::OutputsArrayEnd;
-                    skip = false;
-                }
-                save_spaces.clear();
-                flush_events(skip, &mut writer, &mut save_spaces, &event)?;
-            }
-            (State::ExecutionCount, JsonEvent::Number(_) | JsonEvent::Null) => {
-                state = State::OutputsArrayEnd;
-                skip = false;
-                flush_events(skip, &mut writer, &mut save_spaces, &JsonEvent::Null)?;
-            }
-            (State::MetaData, JsonEvent::EndObject) => {
-                state = State::OutputsArrayEnd;
-                flush_events(skip, &mut writer, &mut save_spaces, &event)?;
-            }
-            (State::Collapsed, JsonEvent::NextObjectValue) => {
-                state = State::MetaData;
-                skip = false;
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let mut failed = 0;
+    let mut uncleared = 0;
+    let mut file_reports = vec![];
+    let mut stdout = std::io::stdout().lock();
+    pool::for_each_ordered(
+        &jobs,
+        inputs.jobs.unwrap_or_else(pool::default_jobs),
+        |job| {
+            let start = Instant::now();
+            let mut stdout = vec![];
+            let result = match &job.options {
+                Ok(options) if inputs.diff => diff_file(&job.notebook, options, color, &mut stdout),
+                Ok(options) => clear_file(output, &job.notebook, options, &mut stdout),
+                Err(err) => Err(anyhow::anyhow!("{:#}", err)),
+            };
+            Processed {
+                result,
+                stdout,
+                elapsed: start.elapsed(),
             }
-            (State::MetaData, JsonEvent::ObjectKey(key)) if key == "collapsed" => {
-                state = State::Collapsed;
-                skip = true;
-            }
-            (_, JsonEvent::WhiteSpace(space)) => {
-                save_spaces.push(space.clone());
-            }
-            _ => {
-                flush_events(skip, &mut writer, &mut save_spaces, &event)?;
+        },
+        |job, processed| {
+            let notebook = &job.notebook;
+            file_reports.push(FileReport {
+                path: notebook.clone(),
+                stats: processed
+                    .result
+                    .as_ref()
+                    .ok()
+                    .map(|report| report.stats.clone()),
+                elapsed_ms: processed.elapsed.as_secs_f64() * 1000.0,
+                error: processed
+                    .result
+                    .as_ref()
+                    .err()
+                    .map(|err| format!("{:#}", err)),
+            });
+            match processed.result {
+                Ok(report) => {
+                    if let Err(err) = stdout.write_all(&processed.stdout) {
+                        eprintln!("{}: {}", notebook.display(), err);
+                    }
+                    if matches!(output, Output::Check) && report.changed {
+                        uncleared += 1;
+                        if report.changed_cells.is_empty() {
+                            println!("{}: would be cleared", notebook.display());
+                        }
+                        for cell in &report.changed_cells {
+                            println!("{}: {} would be cleared", notebook.display(), cell);
+                        }
+                    }
+                }
+                Err(err) => {
+                    eprintln!("{}: {:#}", notebook.display(), err);
+                    failed += 1;
+                }
             }
+        },
+    );
+    stdout.flush()?;
+    drop(stdout);
+
+    if let Some(path) = &inputs.report {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(
+            &mut writer,
+            &Report {
+                files: file_reports,
+            },
+        )?;
+        writeln!(writer)?;
+        writer.flush()?;
+    }
+
+    let processed = jobs.len() - failed;
+    if matches!(output, Output::Check) {
+        eprintln!(
+            "{} processed, {} failed, {} would be cleared",
+            processed, failed, uncleared
+        );
+    } else {
+        eprintln!("{} processed, {} failed", processed, failed);
+    }
+    Ok(if failed == 0 && uncleared == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Clears the notebook at `path`, writing what goes to stdout into `stdout`.
+fn clear_file(
+    output: Output,
+    path: &Path,
+    options: &ClearOptions,
+    stdout: &mut Vec<u8>,
+) -> anyhow::Result<ClearReport> {
+    match output {
+        Output::Inplace if is_stdin(path) => Err(anyhow::anyhow!(
+            "stdin can not be cleared in place, use `nbclo stdout -` instead"
+        )),
+        Output::Inplace => clear_inplace(path, options),
+        Output::Stdout => clear_input(path, stdout, options),
+        Output::Check => clear_input(path, std::io::sink(), options),
+    }
+}
+
+/// Clears the notebook at `path` into `writer`, mapping it in memory if it is a regular file.
+fn clear_input<W: Write>(
+    path: &Path,
+    writer: W,
+    options: &ClearOptions,
+) -> anyhow::Result<ClearReport> {
+    if is_stdin(path) {
+        return clear_notebook(std::io::stdin().lock(), writer, options);
+    }
+    let file = File::open(path)?;
+    match input::map_file(&file)? {
+        Some(notebook) => clear_notebook_slice(&notebook, writer, options),
+        None => clear_notebook(BufReader::new(file), writer, options),
+    }
+}
+
+/// Writes the changes clearing the notebook at `path` would make to `stdout`, without writing
+/// the notebook.
+fn diff_file(
+    path: &Path,
+    options: &ClearOptions,
+    color: bool,
+    stdout: &mut Vec<u8>,
+) -> anyhow::Result<ClearReport> {
+    let mut original = vec![];
+    open_input(path)?.read_to_end(&mut original)?;
+    let mut cleared = vec![];
+    let report = clear_notebook_slice(&original, &mut cleared, options)?;
+
+    let diff = diff::unified_diff(
+        &String::from_utf8_lossy(&original),
+        &String::from_utf8_lossy(&cleared),
+        &path.display().to_string(),
+        color,
+    );
+    stdout.extend_from_slice(diff.as_bytes());
+    Ok(report)
+}
+
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+fn open_input(path: &Path) -> anyhow::Result<Box<dyn BufRead>> {
+    Ok(if is_stdin(path) {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn overrides(args: &[&str]) -> Config {
+        let args = ["nbclo", "config", "show"].iter().chain(args);
+        match Args::try_parse_from(args).unwrap().command {
+            Command::Config(ConfigCommand::Show { clear, .. }) => clear.config(),
+            command => panic!("unexpected command {command:?}"),
         }
+    }
 
-        match (state, event) {
-            (State::Root, JsonEvent::ObjectKey(key)) if key == "cells" => {
-                state = State::Cells;
-            }
-            (State::Cells, JsonEvent::StartArray) => {
-                state = State::CellsArrayStart;
-            }
-            (State::CellsArrayStart | State::OutputsArrayEnd, JsonEvent::ObjectKey(key))
-                if key == "outputs" =>
-            {
-                state = State::Outputs;
-            }
-            (State::CellsArrayStart | State::OutputsArrayEnd, JsonEvent::ObjectKey(key))
-                if key == "execution_count" =>
-            {
-                state = State::ExecutionCount;
-                skip = true;
-            }
-            (State::CellsArrayStart | State::OutputsArrayEnd, JsonEvent::ObjectKey(key))
-                if key == "metadata" =>
-            {
-                state = State::MetaData;
-            }
-            (State::Outputs, JsonEvent::StartArray) => {
-                state = State::OutputsArrayStart;
-                skip = true;
-                stack += 1;
-            }
-            (State::OutputsArrayStart, JsonEvent::StartArray) => {
-                stack += 1;
+    #[test]
+    fn override_config_flags() {
+        let on = Config {
+            max_output_bytes: Some(100),
+            stub_removed_outputs: Some(true),
+            execution_count: Some(ExecutionCount::Renumber),
+            upgrade: Some(true),
+            validate: Some(true),
+            ..Config::default()
+        };
+        let off = Config {
+            stub_removed_outputs: Some(false),
+            execution_count: Some(ExecutionCount::Clear),
+            upgrade: Some(false),
+            validate: Some(false),
+            ..on.clone()
+        };
+        let off_flags = [
+            "--no-stub-removed-outputs",
+            "--clear-count",
+            "--no-upgrade",
+            "--no-validate",
+        ];
+        assert_eq!(on.clone().merge(overrides(&off_flags)), off);
+        let on_flags = [
+            "--max-output-bytes",
+            "100",
+            "--stub-removed-outputs",
+            "--renumber",
+            "--upgrade",
+            "--validate",
+        ];
+        assert_eq!(off.clone().merge(overrides(&on_flags)), on);
+
+        assert_eq!(on.clone().merge(overrides(&[])), on);
+        assert_eq!(
+            overrides(&["--upgrade", "--no-upgrade", "--keep-count", "--renumber"]),
+            Config {
+                upgrade: Some(false),
+                execution_count: Some(ExecutionCount::Renumber),
+                ..Config::default()
             }
-            _ => {}
-        }
+        );
     }
 
-    Ok(())
+    #[test]
+    fn filter_notebooks() {
+        let notebook = r#"{
+ "cells": [
+  {"cell_type": "code", "execution_count": 1, "id": "a", "metadata": {}, "outputs": [], "source": ""},
+  {"cell_type": "raw", "metadata": {}, "source": ""}
+ ],
+ "metadata": {},
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"#;
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("scratch")).unwrap();
+        fs::write(root.join(".nbclo.toml"), "exclude = [\"scratch\"]\n").unwrap();
+        let (kept, excluded) = (root.join("a.ipynb"), root.join("scratch/b.ipynb"));
+        fs::write(&kept, notebook).unwrap();
+        fs::write(&excluded, notebook).unwrap();
+        let filtered = |notebook: &str, path: &Path| {
+            let mut output = vec![];
+            let result = filter(notebook.as_bytes(), &mut output, path, Config::default());
+            (result.map_err(|err| err.to_string()), output)
+        };
+
+        assert_eq!(
+            filtered(notebook, &kept),
+            (
+                Ok(()),
+                notebook
+                    .replace("\"execution_count\": 1", "\"execution_count\": null")
+                    .into_bytes()
+            )
+        );
+        assert_eq!(
+            filtered(notebook, &excluded),
+            (Ok(()), notebook.as_bytes().to_vec())
+        );
+
+        // Nothing is written for an invalid notebook.
+        for invalid in [
+            notebook.replace("\"raw\"", "\"text\""),
+            notebook.replace(" \"nbformat\": 4,\n", ""),
+            notebook[..notebook.len() / 2].to_string(),
+        ] {
+            let (result, output) = filtered(&invalid, &kept);
+            assert!(result.is_err());
+            assert!(output.is_empty(), "{}", String::from_utf8_lossy(&output));
+        }
+    }
 }
@@ -0,0 +1,205 @@
+use crate::events::{
+    entries, entry_key, entry_value, map_entries, map_entry_value, serialized_len, Events,
+};
+use crate::rules::Indentation;
+use json_event_parser_witespace::JsonEvent;
+use std::borrow::Cow;
+
+/// Selects the MIME types kept in the `data` bundles of `display_data` and `execute_result`
+/// outputs. Patterns are MIME types such as `image/png`, or a whole family such as `image/*`.
+#[derive(Debug, Clone, Default)]
+pub struct MimeFilter {
+    pub strip: Vec<String>,
+    pub keep: Vec<String>,
+}
+
+impl MimeFilter {
+    pub fn is_active(&self) -> bool {
+        !self.strip.is_empty() || !self.keep.is_empty()
+    }
+
+    fn keeps(&self, mime: &str) -> bool {
+        let matches = |pattern: &String| match pattern.strip_suffix("/*") {
+            Some(family) => mime
+                .split_once('/')
+                .is_some_and(|(mime_family, _)| mime_family == family),
+            None => pattern == mime,
+        };
+        !self.strip.iter().any(matches) && (self.keep.is_empty() || self.keep.iter().any(matches))
+    }
+
+    /// Filters the `data` bundle of a single output, and the matching entries of its `metadata`.
+    ///
+    /// Returns `None` if the output has a `data` bundle with nothing left in it.
+    pub fn filter_output(&self, output: Events) -> Option<Events> {
+        let mut emptied = false;
+        let output = map_entries(output, |entry| match entry_key(&entry) {
+            Some("data") => Some(map_entry_value(entry, |data| {
+                let mut kept = 0;
+                let data = map_entries(data, |entry| {
+                    let keep = entry_key(&entry).is_some_and(|mime| self.keeps(mime));
+                    kept += usize::from(keep);
+                    keep.then_some(entry)
+                });
+                emptied = kept == 0;
+                data
+            })),
+            Some("metadata") => Some(map_entry_value(entry, |metadata| {
+                map_entries(metadata, |entry| {
+                    let mime = entry_key(&entry).filter(|key| key.contains('/'));
+                    match mime {
+                        Some(mime) if !self.keeps(mime) => None,
+                        _ => Some(entry),
+                    }
+                })
+            })),
+            _ => Some(entry),
+        });
+        (!emptied).then_some(output)
+    }
+}
+
+/// Removes outputs whose serialized size is larger than `max_bytes`.
+#[derive(Debug, Clone)]
+pub struct SizeLimit {
+    pub max_bytes: usize,
+    /// Replace removed outputs with a `stream` output saying what was removed.
+    pub stub: bool,
+}
+
+impl SizeLimit {
+    pub fn limit_output(&self, output: Events, indentation: &Indentation) -> Option<Events> {
+        let size = serialized_len(&output);
+        if size <= self.max_bytes {
+            Some(output)
+        } else if self.stub {
+            Some(stub_output(&output, size, indentation))
+        } else {
+            None
+        }
+    }
+}
+
+/// A `stream` output standing for the removed `output`, written the way Jupyter writes it
+/// at `indentation`, or without whitespace in a compact array.
+fn stub_output(output: &[JsonEvent], size: usize, indentation: &Indentation) -> Events {
+    let text = format!(
+        "[output removed: {} {}]\n",
+        format_size(size),
+        output_kind(output)
+    );
+    let space = |space: String| JsonEvent::WhiteSpace(Cow::Owned(space));
+    let (entry, after_key, line, closing) = match &indentation.line {
+        Some(indent) => {
+            let step = &indentation.step;
+            (
+                vec![space(format!("\n{indent}{step}"))],
+                vec![space(" ".to_string())],
+                vec![space(format!("\n{indent}{step}{step}"))],
+                vec![space(format!("\n{indent}"))],
+            )
+        }
+        None => (vec![], vec![], vec![], vec![]),
+    };
+    let text = [
+        vec![JsonEvent::StartArray],
+        line,
+        vec![JsonEvent::String(Cow::Owned(text))],
+        entry.clone(),
+        vec![JsonEvent::EndArray],
+    ]
+    .concat();
+    // Sorted as nbformat writes them, the text split in lines.
+    let entries = [
+        ("name", vec![JsonEvent::String(Cow::Borrowed("stdout"))]),
+        (
+            "output_type",
+            vec![JsonEvent::String(Cow::Borrowed("stream"))],
+        ),
+        ("text", text),
+    ];
+    let mut stub = vec![JsonEvent::StartObject];
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            stub.push(JsonEvent::NextObjectValue);
+        }
+        stub.extend(entry.iter().cloned());
+        stub.push(JsonEvent::ObjectKey(Cow::Borrowed(key)));
+        stub.extend(after_key.iter().cloned());
+        stub.extend(value);
+    }
+    stub.extend(closing);
+    stub.push(JsonEvent::EndObject);
+    stub
+}
+
+/// The largest MIME type in the `data` of `output`, or its `output_type` if it has no data.
+fn output_kind(output: &[JsonEvent]) -> String {
+    let output_entries = entries(output);
+    let largest_mime = output_entries
+        .iter()
+        .find(|entry| entry_key(entry) == Some("data"))
+        .and_then(|data| {
+            entries(entry_value(data))
+                .into_iter()
+                .max_by_key(|entry| serialized_len(entry))
+                .and_then(entry_key)
+        });
+    let output_type = || {
+        output_entries
+            .iter()
+            .find(|entry| entry_key(entry) == Some("output_type"))
+            .and_then(|entry| match entry_value(entry) {
+                [JsonEvent::String(output_type)] => Some(output_type.as_ref()),
+                _ => None,
+            })
+    };
+    largest_mime
+        .or_else(output_type)
+        .unwrap_or("output")
+        .to_string()
+}
+
+fn format_size(size: usize) -> String {
+    const UNITS: [&str; 4] = ["kB", "MB", "GB", "TB"];
+    if size < 1000 {
+        return format!("{} B", size);
+    }
+    let mut size = size as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mime_patterns() {
+        let filter = MimeFilter {
+            strip: vec!["image/*".to_string(), "text/html".to_string()],
+            keep: vec![],
+        };
+        assert!(filter.keeps("text/plain"));
+        assert!(!filter.keeps("text/html"));
+        assert!(!filter.keeps("image/png"));
+
+        let filter = MimeFilter {
+            strip: vec![],
+            keep: vec!["text/plain".to_string()],
+        };
+        assert!(filter.keeps("text/plain"));
+        assert!(!filter.keeps("application/vnd.jupyter.widget-view+json"));
+    }
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2_300_000), "2.3 MB");
+        assert_eq!(format_size(1_500), "1.5 kB");
+    }
+}
@@ -0,0 +1,110 @@
+//! Tracking of the JSON path of the value being read, such as `cells[3].metadata.collapsed`.
+
+use json_event_parser_witespace::JsonEvent;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Follows the event stream to know the path of the value being read.
+///
+/// After an `ObjectKey` event, or before the event starting an array element, the path is that
+/// of the upcoming value. A complete value leaves the path as it was, so a caller may skip the
+/// events of a whole value without observing them.
+#[derive(Debug, Default)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+    /// For each open container, whether it has pushed a segment.
+    frames: Vec<bool>,
+}
+
+impl JsonPath {
+    pub fn observe(&mut self, event: &JsonEvent) {
+        match event {
+            JsonEvent::ObjectKey(key) => match self.frames.last_mut() {
+                Some(true) => {
+                    if let Some(last) = self.segments.last_mut() {
+                        *last = Segment::Key(key.to_string());
+                    }
+                }
+                Some(has_segment) => {
+                    *has_segment = true;
+                    self.segments.push(Segment::Key(key.to_string()));
+                }
+                None => {}
+            },
+            JsonEvent::StartObject => self.frames.push(false),
+            JsonEvent::StartArray => {
+                self.frames.push(true);
+                self.segments.push(Segment::Index(0));
+            }
+            JsonEvent::NextArrayValue => {
+                if let Some(Segment::Index(index)) = self.segments.last_mut() {
+                    *index += 1;
+                }
+            }
+            JsonEvent::EndObject | JsonEvent::EndArray if self.frames.pop() == Some(true) => {
+                self.segments.pop();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Key(key) if key.contains(['.', '[', ']', '"']) || key.is_empty() => {
+                    write!(f, "[{:?}]", key)?
+                }
+                Segment::Key(key) if i == 0 => write!(f, "{}", key)?,
+                Segment::Key(key) => write!(f, ".{}", key)?,
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use json_event_parser_witespace::JsonReader;
+
+    #[test]
+    fn track_paths() {
+        let json = r#"{"cells": [{"metadata": {}}, {"metadata": {"collapsed": true, "a.b": []}}], "nbformat": 4}"#;
+        let mut reader = JsonReader::from_reader(json.as_bytes());
+        let mut buffer = vec![];
+        let mut path = JsonPath::default();
+        let mut paths = vec![];
+        loop {
+            let event = reader.read_event(&mut buffer).unwrap();
+            match event {
+                JsonEvent::Eof => break,
+                JsonEvent::Boolean(_) | JsonEvent::Number(_) | JsonEvent::EndArray => {
+                    paths.push(path.to_string())
+                }
+                _ => {}
+            }
+            path.observe(&event);
+        }
+        assert_eq!(
+            paths,
+            vec![
+                "cells[1].metadata.collapsed",
+                r#"cells[1].metadata["a.b"][0]"#,
+                "cells[1]",
+                "nbformat"
+            ]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! A pool of worker threads processing items in parallel, with results handled in order.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// The number of workers used by default, one per CPU.
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |jobs| jobs.get())
+}
+
+/// Calls `f` on each of `items` from `jobs` worker threads, and `sink` on each result in the
+/// order of `items`.
+///
+/// `sink` runs on the calling thread, as soon as the results of the items before have been handled.
+pub fn for_each_ordered<T, R, F, S>(items: &[T], jobs: usize, f: F, mut sink: S)
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    S: FnMut(&T, R),
+{
+    let next = AtomicUsize::new(0);
+    let jobs = jobs.clamp(1, items.len().max(1));
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (next, f) = (&next, &f);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                if sender.send((index, f(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut next_result = 0;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&next_result) {
+                sink(&items[next_result], result);
+                next_result += 1;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn results_in_order() {
+        let items: Vec<u64> = (0..50).collect();
+        let mut results = vec![];
+        for_each_ordered(
+            &items,
+            8,
+            |item| {
+                // Finish the items out of order.
+                thread::sleep(Duration::from_millis((50 - item) % 7));
+                item * 2
+            },
+            |item, result| results.push((*item, result)),
+        );
+        assert_eq!(
+            results,
+            items
+                .iter()
+                .map(|item| (*item, item * 2))
+                .collect::<Vec<_>>()
+        );
+
+        let mut count = 0;
+        for_each_ordered(&[] as &[u64], 0, |item| *item, |_, _| count += 1);
+        assert_eq!(count, 0);
+    }
+}
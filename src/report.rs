@@ -0,0 +1,194 @@
+use json_event_parser_witespace::JsonEvent;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What clearing a notebook changed, or would change.
+#[derive(Debug, Default, PartialEq)]
+pub struct ClearReport {
+    pub changed: bool,
+    pub changed_cells: Vec<ChangedCell>,
+    pub stats: ClearStats,
+}
+
+/// Counts of what clearing a notebook removed.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ClearStats {
+    pub cells: usize,
+    /// Outputs removed, by `output_type`. Outputs replaced by a stub are not counted.
+    pub outputs_removed: BTreeMap<String, usize>,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// `bytes_before - bytes_after`, negative if clearing made the notebook larger.
+    pub bytes_saved: i64,
+    pub execution_counts_reset: usize,
+    pub metadata_keys_removed: usize,
+}
+
+/// A cell whose outputs, execution count or metadata are modified by clearing.
+#[derive(Debug, PartialEq)]
+pub struct ChangedCell {
+    pub index: usize,
+    pub id: Option<String>,
+}
+
+impl fmt::Display for ChangedCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.id {
+            Some(id) => write!(f, "cell {} (id {})", self.index, id),
+            None => write!(f, "cell {}", self.index),
+        }
+    }
+}
+
+/// Depth of the `cells` array in an nbformat 4 notebook, counting the root object.
+const CELLS_DEPTH: usize = 2;
+/// Depth of the `cells` arrays of an nbformat 3 notebook, inside of `worksheets[*]`.
+const WORKSHEET_CELLS_DEPTH: usize = CELLS_DEPTH + 2;
+
+#[derive(Debug)]
+struct Cell {
+    index: usize,
+    id: Option<String>,
+    tags: Vec<String>,
+    in_metadata: bool,
+    in_tags: bool,
+    metadata_read: bool,
+    changed: bool,
+}
+
+/// Follows the event stream to know which cell, if any, the current event belongs to.
+///
+/// This only counts nesting depth, so it works independently of the clearing state machine.
+#[derive(Debug, Default)]
+pub(crate) struct CellCursor {
+    depth: usize,
+    key: Option<String>,
+    in_worksheets: bool,
+    /// The depth of the `cells` array being read, if any.
+    cells_depth: Option<usize>,
+    next_index: usize,
+    current: Option<Cell>,
+    report: ClearReport,
+}
+
+impl CellCursor {
+    pub fn observe(&mut self, event: &JsonEvent) {
+        let cell_depth = self.cells_depth.map_or(0, |depth| depth + 1);
+        match event {
+            JsonEvent::WhiteSpace(_) => {}
+            JsonEvent::ObjectKey(key) => {
+                self.key = Some(key.to_string());
+            }
+            JsonEvent::StartObject | JsonEvent::StartArray => {
+                let key = self.key.take();
+                self.depth += 1;
+                let is_array = *event == JsonEvent::StartArray;
+                match &mut self.current {
+                    Some(cell) if self.depth == cell_depth + 1 => {
+                        cell.in_metadata = !is_array && key.as_deref() == Some("metadata");
+                    }
+                    Some(cell) if self.depth == cell_depth + 2 => {
+                        cell.in_tags =
+                            cell.in_metadata && is_array && key.as_deref() == Some("tags");
+                    }
+                    Some(_) => {}
+                    None if self.cells_depth.is_some() && self.depth == cell_depth && !is_array => {
+                        let index = self.next_index;
+                        self.next_index += 1;
+                        self.current = Some(Cell {
+                            index,
+                            id: None,
+                            tags: vec![],
+                            in_metadata: false,
+                            in_tags: false,
+                            metadata_read: false,
+                            changed: false,
+                        });
+                    }
+                    None if is_array && key.as_deref() == Some("cells") => {
+                        if self.depth == CELLS_DEPTH
+                            || (self.in_worksheets && self.depth == WORKSHEET_CELLS_DEPTH)
+                        {
+                            self.cells_depth = Some(self.depth);
+                        }
+                    }
+                    None => {
+                        if is_array && self.depth == CELLS_DEPTH {
+                            self.in_worksheets = key.as_deref() == Some("worksheets");
+                        }
+                    }
+                }
+            }
+            JsonEvent::EndObject | JsonEvent::EndArray => {
+                if self.depth == cell_depth {
+                    if let Some(cell) = self.current.take() {
+                        if cell.changed {
+                            self.report.changed_cells.push(ChangedCell {
+                                index: cell.index,
+                                id: cell.id,
+                            });
+                        }
+                    }
+                } else if let Some(cell) = &mut self.current {
+                    if self.depth == cell_depth + 1 && cell.in_metadata {
+                        cell.in_metadata = false;
+                        cell.metadata_read = true;
+                    } else if self.depth == cell_depth + 2 {
+                        cell.in_tags = false;
+                    }
+                } else if self.cells_depth == Some(self.depth) {
+                    self.cells_depth = None;
+                } else if self.depth == CELLS_DEPTH {
+                    self.in_worksheets = false;
+                }
+                self.depth -= 1;
+            }
+            JsonEvent::String(value) => {
+                let key = self.key.take();
+                if let Some(cell) = &mut self.current {
+                    if self.depth == cell_depth && key.as_deref() == Some("id") {
+                        cell.id = Some(value.to_string());
+                    } else if cell.in_tags && self.depth == cell_depth + 2 {
+                        cell.tags.push(value.to_string());
+                    }
+                }
+            }
+            _ => {
+                self.key = None;
+            }
+        }
+    }
+
+    /// Whether the current event is inside a cell object.
+    pub fn in_cell(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Whether the `metadata` object of the current cell has been read entirely.
+    pub fn cell_metadata_read(&self) -> bool {
+        self.current.as_ref().is_some_and(|cell| cell.metadata_read)
+    }
+
+    /// The `metadata.tags` of the current cell read so far.
+    pub fn cell_tags(&self) -> &[String] {
+        self.current.as_ref().map_or(&[], |cell| &cell.tags)
+    }
+
+    /// Records that the current event is modified or dropped.
+    pub fn mark_changed(&mut self) {
+        self.report.changed = true;
+        if let Some(cell) = &mut self.current {
+            cell.changed = true;
+        }
+    }
+
+    /// The number of cells read so far.
+    pub fn cell_count(&self) -> usize {
+        self.next_index
+    }
+
+    pub fn into_report(self) -> ClearReport {
+        self.report
+    }
+}
@@ -0,0 +1,721 @@
+//! Declarative rules, keyed by path patterns, applied to the event stream as it is written.
+
+use crate::events::Events;
+use crate::path::{JsonPath, Segment};
+use anyhow::bail;
+use json_event_parser_witespace::{JsonEvent, JsonWriter};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Write;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternSegment {
+    Key(String),
+    /// `*`, any single key.
+    AnyKey,
+    Index(usize),
+    /// `[*]`, any array element.
+    AnyIndex,
+}
+
+/// A path pattern such as `cells[*].metadata.collapsed`, matching paths of the same length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathPattern(Vec<PatternSegment>);
+
+impl PathPattern {
+    pub fn new(segments: Vec<PatternSegment>) -> Self {
+        Self(segments)
+    }
+
+    pub fn parse(pattern: &str) -> anyhow::Result<Self> {
+        let mut segments = vec![];
+        for part in pattern.split('.') {
+            let (key, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+            match key {
+                "" if indices.is_empty() => bail!("invalid path pattern `{}`", pattern),
+                "" => {}
+                "*" => segments.push(PatternSegment::AnyKey),
+                key => segments.push(PatternSegment::Key(key.to_string())),
+            }
+            while !indices.is_empty() {
+                let Some((index, rest)) = indices
+                    .strip_prefix('[')
+                    .and_then(|indices| indices.split_once(']'))
+                else {
+                    bail!("invalid path pattern `{}`", pattern);
+                };
+                segments.push(match index {
+                    "*" => PatternSegment::AnyIndex,
+                    index => match index.parse() {
+                        Ok(index) => PatternSegment::Index(index),
+                        Err(_) => bail!("invalid index `{}` in path pattern `{}`", index, pattern),
+                    },
+                });
+                indices = rest;
+            }
+        }
+        Ok(Self(segments))
+    }
+
+    pub fn segments(&self) -> &[PatternSegment] {
+        &self.0
+    }
+
+    pub fn matches(&self, path: &[Segment]) -> bool {
+        self.0.len() == path.len()
+            && self
+                .0
+                .iter()
+                .zip(path)
+                .all(|(pattern, segment)| match (pattern, segment) {
+                    (PatternSegment::AnyKey, Segment::Key(_)) => true,
+                    (PatternSegment::AnyIndex, Segment::Index(_)) => true,
+                    (PatternSegment::Key(pattern), Segment::Key(key)) => pattern == key,
+                    (PatternSegment::Index(pattern), Segment::Index(index)) => pattern == index,
+                    _ => false,
+                })
+    }
+}
+
+/// Edits a buffered value; returning `None` removes it.
+pub type Edit = Rc<dyn Fn(Events, &Indentation) -> Option<Events>>;
+
+/// How an edited value is indented, taken from the whitespace in front of it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Indentation {
+    /// The whitespace after the last line break in front of the value, `None` if the value does
+    /// not start a line.
+    pub line: Option<String>,
+    /// The indentation of one nesting level.
+    pub step: String,
+}
+
+impl Indentation {
+    /// The indentation of a value at `depth` after the whitespace `before` it, assuming every
+    /// level is indented alike, with one space as Jupyter does otherwise.
+    fn new(before: &str, depth: usize) -> Self {
+        let line = before
+            .rfind('\n')
+            .map(|newline| before[newline + 1..].to_string());
+        let step = match &line {
+            Some(line) if depth > 0 && !line.is_empty() && line.len() % depth == 0 => {
+                line[..line.len() / depth].to_string()
+            }
+            _ => " ".to_string(),
+        };
+        Self { line, step }
+    }
+}
+
+/// What happens to the values matching a rule. Rules still apply to the values inside kept and
+/// edited values.
+#[derive(Clone)]
+pub enum Action {
+    /// Writes the value as it is, so that the following rules do not apply to it.
+    Keep,
+    /// Removes the value, together with its key in an object.
+    Remove,
+    /// Writes these events instead of the value.
+    Replace(Events),
+    /// Buffers the value and writes the result of the function instead.
+    Edit(Edit),
+}
+
+impl fmt::Debug for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Keep => write!(f, "Keep"),
+            Action::Remove => write!(f, "Remove"),
+            Action::Replace(events) => f.debug_tuple("Replace").field(events).finish(),
+            Action::Edit(_) => write!(f, "Edit(..)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub pattern: PathPattern,
+    pub action: Action,
+}
+
+/// Rules in order of precedence: the first one matching a path applies.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet(Vec<Rule>);
+
+impl RuleSet {
+    pub fn push(&mut self, pattern: PathPattern, action: Action) {
+        self.0.push(Rule { pattern, action });
+    }
+
+    pub fn action(&self, path: &[Segment]) -> Option<&Action> {
+        self.0
+            .iter()
+            .find(|rule| rule.pattern.matches(path))
+            .map(|rule| &rule.action)
+    }
+}
+
+/// A value changed by a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub path: Vec<Segment>,
+    pub kind: ChangeKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    /// Removed by a `Remove` rule or by an edit.
+    Removed,
+    /// Replaced by these events.
+    Replaced(Events),
+    Edited,
+}
+
+/// A value whose events are not written as they are read.
+#[derive(Debug)]
+enum Held {
+    /// Removed, or replaced by events already written.
+    Skipped {
+        replacement: Option<Events>,
+        matched: usize,
+        changed: bool,
+    },
+    Edited {
+        edit: EditFn,
+        /// The whitespace in front of the entry, and its key in an object.
+        before: String,
+        key: Option<JsonEvent<'static>>,
+        events: Events,
+    },
+}
+
+struct EditFn(Edit);
+
+impl fmt::Debug for EditFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EditFn")
+    }
+}
+
+#[derive(Debug)]
+struct HeldValue {
+    held: Held,
+    depth: usize,
+    started: bool,
+}
+
+#[derive(Debug)]
+struct Container {
+    separator: JsonEvent<'static>,
+    written: usize,
+    removed: bool,
+    removed_last: bool,
+    /// The whitespace in front of the first entry while all entries so far are removed, for
+    /// the first entry kept to take in place of its own.
+    leading: String,
+}
+
+/// Writes events, applying the first matching rule to each value.
+///
+/// Removing an entry also removes one comma next to it, and the whitespace in front of it, or
+/// for a first entry the whitespace after its comma. As with [`crate::events::map_entries`], an
+/// object or array left empty is written without whitespace inside.
+pub struct RuleWriter<W: Write> {
+    writer: JsonWriter<W>,
+    path: JsonPath,
+    containers: Vec<Container>,
+    /// The whitespace read since the last event written.
+    spaces: String,
+    /// The action for the value of the key just written.
+    pending: Option<Action>,
+    held: Option<HeldValue>,
+    /// Set while replaying an edited value, whose own rule must not apply again.
+    replaying: bool,
+    /// Set after the separator of a removed first entry, until the next entry starts.
+    dropping_spaces: bool,
+    changes: Vec<Change>,
+}
+
+impl<W: Write> RuleWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: JsonWriter::from_writer(writer),
+            path: JsonPath::default(),
+            containers: vec![],
+            spaces: String::new(),
+            pending: None,
+            held: None,
+            replaying: false,
+            dropping_spaces: false,
+            changes: vec![],
+        }
+    }
+
+    /// Writes `event` according to the first of `rules` matching its path.
+    ///
+    /// The values changed by rules are then given by [`Self::drain_changes`].
+    ///
+    /// Events are only copied when a value is held to be edited.
+    pub fn write_event(&mut self, event: JsonEvent<'_>, rules: &[&RuleSet]) -> anyhow::Result<()> {
+        if let Some(held) = &mut self.held {
+            if !matches!(event, JsonEvent::WhiteSpace(_)) {
+                held.started = true;
+            }
+            match event {
+                JsonEvent::StartObject | JsonEvent::StartArray => held.depth += 1,
+                JsonEvent::EndObject | JsonEvent::EndArray => held.depth -= 1,
+                _ => {}
+            }
+            let complete = held.started && held.depth == 0;
+            match &mut held.held {
+                Held::Skipped {
+                    replacement,
+                    matched,
+                    changed,
+                } => {
+                    if !matches!(event, JsonEvent::WhiteSpace(_)) {
+                        match replacement {
+                            Some(replacement) if replacement.get(*matched) == Some(&event) => {
+                                *matched += 1;
+                            }
+                            _ => *changed = true,
+                        }
+                    }
+                }
+                Held::Edited { events, .. } => events.push(event.into_owned()),
+            }
+            return if complete {
+                self.release(rules)
+            } else {
+                Ok(())
+            };
+        }
+
+        if !matches!(event, JsonEvent::WhiteSpace(_)) {
+            self.dropping_spaces = false;
+        }
+        match &event {
+            JsonEvent::WhiteSpace(space) => {
+                if !self.dropping_spaces {
+                    self.spaces.push_str(space);
+                }
+                return Ok(());
+            }
+            JsonEvent::NextObjectValue | JsonEvent::NextArrayValue => {
+                self.path.observe(&event);
+                match self.containers.last_mut() {
+                    // Whitespace after a removed entry goes with it, and the next entry takes
+                    // the place of a removed first one, with its whitespace.
+                    Some(container) if container.removed_last && container.written == 0 => {
+                        self.spaces = std::mem::take(&mut container.leading);
+                        self.dropping_spaces = true;
+                    }
+                    Some(container) if container.removed_last => self.spaces.clear(),
+                    _ => self.write_spaces()?,
+                }
+                return Ok(());
+            }
+            JsonEvent::ObjectKey(_) => {
+                self.path.observe(&event);
+                let action = self.lookup(rules);
+                return match action {
+                    Some(Action::Remove) => {
+                        self.drop_spaces();
+                        self.hold(Held::Skipped {
+                            replacement: None,
+                            matched: 0,
+                            changed: true,
+                        });
+                        Ok(())
+                    }
+                    Some(Action::Edit(edit)) => {
+                        let before = std::mem::take(&mut self.spaces);
+                        self.hold(Held::Edited {
+                            edit: EditFn(edit),
+                            before,
+                            key: Some(event.into_owned()),
+                            events: vec![],
+                        });
+                        Ok(())
+                    }
+                    action => {
+                        self.start_entry()?;
+                        self.write_spaces()?;
+                        self.writer.write_event(&event)?;
+                        self.pending = action;
+                        Ok(())
+                    }
+                };
+            }
+            JsonEvent::EndObject | JsonEvent::EndArray => {
+                if let Some(container) = self.containers.pop() {
+                    if container.written == 0 && container.removed {
+                        self.spaces.clear();
+                    }
+                }
+                self.write_spaces()?;
+                self.path.observe(&event);
+                self.writer.write_event(&event)?;
+                return Ok(());
+            }
+            JsonEvent::Eof => return Ok(()),
+            _ => {}
+        }
+
+        // `event` starts a value.
+        let in_object = self
+            .containers
+            .last()
+            .is_some_and(|container| container.separator == JsonEvent::NextObjectValue);
+        let action = if std::mem::take(&mut self.replaying) {
+            Some(Action::Keep)
+        } else if in_object {
+            self.pending.take()
+        } else {
+            self.lookup(rules)
+        };
+        match action {
+            Some(Action::Remove) => {
+                self.drop_spaces();
+                self.hold(Held::Skipped {
+                    replacement: None,
+                    matched: 0,
+                    changed: true,
+                });
+                self.write_event(event, rules)
+            }
+            Some(Action::Edit(edit)) => {
+                let before = std::mem::take(&mut self.spaces);
+                self.hold(Held::Edited {
+                    edit: EditFn(edit),
+                    before,
+                    key: None,
+                    events: vec![],
+                });
+                self.write_event(event, rules)
+            }
+            Some(Action::Replace(replacement)) => {
+                if !in_object {
+                    self.start_entry()?;
+                }
+                self.write_spaces()?;
+                for replacement_event in &replacement {
+                    self.writer.write_event(replacement_event)?;
+                }
+                self.hold(Held::Skipped {
+                    replacement: Some(replacement),
+                    matched: 0,
+                    changed: false,
+                });
+                self.write_event(event, rules)
+            }
+            Some(Action::Keep) | None => {
+                if !in_object {
+                    self.start_entry()?;
+                }
+                self.write_spaces()?;
+                let separator = match event {
+                    JsonEvent::StartObject => Some(JsonEvent::NextObjectValue),
+                    JsonEvent::StartArray => Some(JsonEvent::NextArrayValue),
+                    _ => None,
+                };
+                self.path.observe(&event);
+                self.writer.write_event(&event)?;
+                if let Some(separator) = separator {
+                    self.containers.push(Container {
+                        separator,
+                        written: 0,
+                        removed: false,
+                        removed_last: false,
+                        leading: String::new(),
+                    });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// The values changed since the last call, in the order they were completed.
+    pub fn drain_changes(&mut self) -> std::vec::Drain<'_, Change> {
+        self.changes.drain(..)
+    }
+
+    /// Writes the whitespace trailing the document.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.write_spaces()
+    }
+
+    fn lookup(&self, rules: &[&RuleSet]) -> Option<Action> {
+        rules
+            .iter()
+            .find_map(|rules| rules.action(self.path.segments()))
+            .cloned()
+    }
+
+    fn hold(&mut self, held: Held) {
+        self.held = Some(HeldValue {
+            held,
+            depth: 0,
+            started: false,
+        });
+    }
+
+    /// Writes the separator in front of an entry that is kept, if it is not the first one.
+    fn start_entry(&mut self) -> anyhow::Result<()> {
+        if let Some(container) = self.containers.last_mut() {
+            container.removed_last = false;
+            if container.written > 0 {
+                self.writer.write_event(&container.separator)?;
+            }
+            container.written += 1;
+        }
+        Ok(())
+    }
+
+    /// Drops the whitespace in front of a removed entry, keeping it for the next entry if the
+    /// removed one is the first.
+    fn drop_spaces(&mut self) {
+        match self.containers.last_mut() {
+            Some(container) if container.written == 0 => {
+                container.leading = std::mem::take(&mut self.spaces);
+            }
+            _ => self.spaces.clear(),
+        }
+    }
+
+    fn remove_entry(&mut self) {
+        if let Some(container) = self.containers.last_mut() {
+            container.removed = true;
+            container.removed_last = true;
+        }
+    }
+
+    /// Finishes the value held by `self.held` once all of its events have been read.
+    fn release(&mut self, rules: &[&RuleSet]) -> anyhow::Result<()> {
+        let Some(HeldValue { held, .. }) = self.held.take() else {
+            return Ok(());
+        };
+        match held {
+            Held::Skipped {
+                replacement: None, ..
+            } => {
+                self.remove_entry();
+                self.record(ChangeKind::Removed);
+            }
+            Held::Skipped {
+                replacement: Some(replacement),
+                matched,
+                changed,
+            } => {
+                if changed || matched != replacement.len() {
+                    self.record(ChangeKind::Replaced(replacement));
+                }
+            }
+            Held::Edited {
+                edit,
+                before,
+                key,
+                events,
+            } => {
+                let value_start = events
+                    .iter()
+                    .position(|event| !matches!(event, JsonEvent::WhiteSpace(_)))
+                    .unwrap_or(events.len());
+                let value = events[value_start..].to_vec();
+                let indentation = Indentation::new(&before, self.path.segments().len());
+                let Some(edited) = (edit.0)(value.clone(), &indentation) else {
+                    self.spaces = before;
+                    self.drop_spaces();
+                    self.remove_entry();
+                    self.record(ChangeKind::Removed);
+                    return Ok(());
+                };
+                if edited != value {
+                    self.record(ChangeKind::Edited);
+                }
+                self.spaces = before;
+                if let Some(key) = key {
+                    self.start_entry()?;
+                    self.write_spaces()?;
+                    self.writer.write_event(&key)?;
+                } else {
+                    // The entry is started by its replayed value.
+                }
+                for event in &events[..value_start] {
+                    if let JsonEvent::WhiteSpace(space) = event {
+                        self.spaces.push_str(space);
+                    }
+                }
+                self.replaying = true;
+                for event in edited {
+                    self.write_event(event, rules)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, kind: ChangeKind) {
+        self.changes.push(Change {
+            path: self.path.segments().to_vec(),
+            kind,
+        });
+    }
+
+    fn write_spaces(&mut self) -> anyhow::Result<()> {
+        if !self.spaces.is_empty() {
+            self.writer
+                .write_event(&JsonEvent::WhiteSpace(Cow::Borrowed(&self.spaces)))?;
+            self.spaces.clear();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use json_event_parser_witespace::JsonReader;
+
+    fn apply(json: &str, rules: &RuleSet) -> (String, bool) {
+        let mut reader = JsonReader::from_reader(json.as_bytes());
+        let mut buffer = vec![];
+        let mut output = vec![];
+        let mut writer = RuleWriter::new(&mut output);
+        let mut changed = false;
+        loop {
+            match reader.read_event(&mut buffer).unwrap() {
+                JsonEvent::Eof => break,
+                event => {
+                    writer.write_event(event, &[rules]).unwrap();
+                    changed |= writer.drain_changes().count() > 0;
+                }
+            }
+        }
+        writer.finish().unwrap();
+        (String::from_utf8(output).unwrap(), changed)
+    }
+
+    fn rules(rules: &[(&str, Action)]) -> RuleSet {
+        let mut set = RuleSet::default();
+        for (pattern, action) in rules {
+            set.push(PathPattern::parse(pattern).unwrap(), action.clone());
+        }
+        set
+    }
+
+    #[test]
+    fn parse_patterns() {
+        assert_eq!(
+            PathPattern::parse("cells[*].metadata.*").unwrap(),
+            PathPattern::new(vec![
+                PatternSegment::Key("cells".to_string()),
+                PatternSegment::AnyIndex,
+                PatternSegment::Key("metadata".to_string()),
+                PatternSegment::AnyKey,
+            ])
+        );
+        assert_eq!(
+            PathPattern::parse("a[0][*]").unwrap(),
+            PathPattern::new(vec![
+                PatternSegment::Key("a".to_string()),
+                PatternSegment::Index(0),
+                PatternSegment::AnyIndex,
+            ])
+        );
+        assert!(PathPattern::parse("a..b").is_err());
+        assert!(PathPattern::parse("a[x]").is_err());
+        assert!(PathPattern::parse("a[0").is_err());
+    }
+
+    #[test]
+    fn remove_entries() {
+        let json = "{\n \"a\": 1,\n \"b\": [1, 2, 3],\n \"c\": {\"d\": 3}\n}\n";
+        assert_eq!(apply(json, &RuleSet::default()), (json.to_string(), false));
+        let removed = |pattern| apply(json, &rules(&[(pattern, Action::Remove)]));
+        assert_eq!(
+            removed("a"),
+            (
+                "{\n \"b\": [1, 2, 3],\n \"c\": {\"d\": 3}\n}\n".to_string(),
+                true
+            )
+        );
+        assert_eq!(
+            removed("b[*]"),
+            (
+                "{\n \"a\": 1,\n \"b\": [],\n \"c\": {\"d\": 3}\n}\n".to_string(),
+                true
+            )
+        );
+        assert_eq!(
+            removed("b[1]"),
+            (
+                "{\n \"a\": 1,\n \"b\": [1, 3],\n \"c\": {\"d\": 3}\n}\n".to_string(),
+                true
+            )
+        );
+        assert_eq!(
+            removed("c"),
+            ("{\n \"a\": 1,\n \"b\": [1, 2, 3]\n}\n".to_string(), true)
+        );
+        assert_eq!(removed("*"), ("{}\n".to_string(), true));
+    }
+
+    #[test]
+    fn remove_first_inline_entries() {
+        let json = "{\"a\": 1, \"b\": [1, 2, 3], \"c\": {\"d\": 3}}";
+        let removed = |pattern| apply(json, &rules(&[(pattern, Action::Remove)])).0;
+        assert_eq!(removed("a"), "{\"b\": [1, 2, 3], \"c\": {\"d\": 3}}");
+        assert_eq!(
+            removed("b[0]"),
+            "{\"a\": 1, \"b\": [2, 3], \"c\": {\"d\": 3}}"
+        );
+        assert_eq!(removed("c.d"), "{\"a\": 1, \"b\": [1, 2, 3], \"c\": {}}");
+        let removed = |patterns: &[&'static str]| {
+            let patterns = patterns.iter().map(|pattern| (*pattern, Action::Remove));
+            apply(json, &rules(&patterns.collect::<Vec<_>>())).0
+        };
+        assert_eq!(removed(&["a", "b"]), "{\"c\": {\"d\": 3}}");
+        assert_eq!(removed(&["a", "c"]), "{\"b\": [1, 2, 3]}");
+        assert_eq!(
+            removed(&["b[0]", "b[1]"]),
+            "{\"a\": 1, \"b\": [3], \"c\": {\"d\": 3}}"
+        );
+    }
+
+    #[test]
+    fn keep_replace_and_edit() {
+        let json = "{\"a\": [1, 2], \"b\": {\"c\": 1}, \"d\": null}";
+        let set = rules(&[
+            ("a[0]", Action::Keep),
+            ("a[*]", Action::Replace(vec![JsonEvent::Null])),
+            ("d", Action::Replace(vec![JsonEvent::Null])),
+            (
+                "b",
+                Action::Edit(Rc::new(|value: Events, _: &Indentation| {
+                    Some(
+                        value
+                            .into_iter()
+                            .map(|event| match event {
+                                JsonEvent::ObjectKey(_) => JsonEvent::ObjectKey("e".into()),
+                                event => event,
+                            })
+                            .collect(),
+                    )
+                })),
+            ),
+            ("b.e", Action::Replace(vec![JsonEvent::Boolean(true)])),
+        ]);
+        assert_eq!(
+            apply(json, &set),
+            (
+                "{\"a\": [1, null], \"b\": {\"e\": true}, \"d\": null}".to_string(),
+                true
+            )
+        );
+
+        let unchanged = rules(&[("d", Action::Replace(vec![JsonEvent::Null]))]);
+        assert_eq!(apply(json, &unchanged), (json.to_string(), false));
+    }
+}
@@ -0,0 +1,278 @@
+//! Rewriting of nbformat 3 notebooks into the nbformat 4 layout, as `nbformat.v4.upgrade` does.
+
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The v4 MIME types of the v3 output keys.
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("text", "text/plain"),
+    ("html", "text/html"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpeg", "image/jpeg"),
+    ("latex", "text/latex"),
+    ("json", "application/json"),
+    ("javascript", "application/javascript"),
+];
+
+/// The nbformat 4 minor version notebooks are upgraded to, the first one with cell ids.
+const NBFORMAT_MINOR: u64 = 5;
+
+/// Rewrites `notebook` into the nbformat 4 layout if it is an nbformat 3 notebook.
+///
+/// The upgraded notebook is written the way Jupyter writes notebooks, with sorted keys and one
+/// space of indentation. Returns `None` for notebooks of any other version.
+pub fn upgrade(notebook: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut notebook: Value = serde_json::from_slice(notebook).context("invalid notebook")?;
+    let Some(root) = notebook.as_object_mut() else {
+        return Ok(None);
+    };
+    if root.get("nbformat").and_then(Value::as_u64) != Some(3) {
+        return Ok(None);
+    }
+    upgrade_notebook(root);
+
+    let mut output = vec![];
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b" ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+    notebook.serialize(&mut serializer)?;
+    output.push(b'\n');
+    Ok(Some(output))
+}
+
+fn upgrade_notebook(notebook: &mut Map<String, Value>) {
+    let minor = notebook.remove("nbformat_minor").unwrap_or(Value::from(0));
+    notebook.insert("nbformat".to_string(), Value::from(4));
+    notebook.insert("nbformat_minor".to_string(), Value::from(NBFORMAT_MINOR));
+
+    let mut cells = vec![];
+    if let Some(Value::Array(worksheets)) = notebook.remove("worksheets") {
+        for worksheet in worksheets {
+            if let Value::Object(mut worksheet) = worksheet {
+                if let Some(Value::Array(worksheet_cells)) = worksheet.remove("cells") {
+                    cells.extend(worksheet_cells.into_iter().map(upgrade_cell));
+                }
+            }
+        }
+    }
+    // Ids from the position of cells, so that upgrading the same notebook gives the same ids.
+    for (index, cell) in cells.iter_mut().enumerate() {
+        if let Value::Object(cell) = cell {
+            cell.entry("id")
+                .or_insert_with(|| Value::from(format!("cell-{index}")));
+        }
+    }
+    notebook.insert("cells".to_string(), Value::Array(cells));
+
+    let metadata = object_entry(notebook, "metadata");
+    metadata.remove("name");
+    metadata.remove("signature");
+    metadata.insert("orig_nbformat".to_string(), Value::from(3));
+    metadata.insert("orig_nbformat_minor".to_string(), minor);
+}
+
+fn upgrade_cell(cell: Value) -> Value {
+    let Value::Object(mut cell) = cell else {
+        return cell;
+    };
+    object_entry(&mut cell, "metadata");
+    match cell.get("cell_type").and_then(Value::as_str) {
+        Some("code") => {
+            cell.remove("language");
+            if let Some(collapsed) = cell.remove("collapsed") {
+                object_entry(&mut cell, "metadata").insert("collapsed".to_string(), collapsed);
+            }
+            let source = cell.remove("input").unwrap_or(Value::from(""));
+            cell.insert("source".to_string(), source);
+            let count = cell.remove("prompt_number").unwrap_or(Value::Null);
+            cell.insert("execution_count".to_string(), count);
+            let outputs = match cell.remove("outputs") {
+                Some(Value::Array(outputs)) => outputs.into_iter().map(upgrade_output).collect(),
+                _ => vec![],
+            };
+            cell.insert("outputs".to_string(), Value::Array(outputs));
+        }
+        Some("heading") => {
+            let level = cell.remove("level").and_then(|level| level.as_u64());
+            let source = text(cell.get("source"))
+                .lines()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let hashes = "#".repeat(level.unwrap_or(1) as usize);
+            cell.insert("cell_type".to_string(), Value::from("markdown"));
+            cell.insert(
+                "source".to_string(),
+                Value::from(format!("{hashes} {source}")),
+            );
+        }
+        Some("html") => {
+            cell.insert("cell_type".to_string(), Value::from("markdown"));
+        }
+        _ => {}
+    }
+    Value::Object(cell)
+}
+
+fn upgrade_output(output: Value) -> Value {
+    let Value::Object(mut output) = output else {
+        return output;
+    };
+    let output_type = output.get("output_type").and_then(Value::as_str);
+    match output_type.map(str::to_string).as_deref() {
+        Some(output_type @ ("pyout" | "display_data")) => {
+            object_entry(&mut output, "metadata");
+            if output_type == "pyout" {
+                output.insert("output_type".to_string(), Value::from("execute_result"));
+                let count = output.remove("prompt_number").unwrap_or(Value::Null);
+                output.insert("execution_count".to_string(), count);
+            }
+            let mut data = Map::new();
+            let keys: Vec<_> = output
+                .keys()
+                .filter(|key| {
+                    !["output_type", "execution_count", "metadata"].contains(&key.as_str())
+                })
+                .cloned()
+                .collect();
+            for key in keys {
+                let value = output.remove(&key).expect("the key is in the output");
+                data.insert(mime_type(&key).to_string(), value);
+            }
+            let metadata = object_entry(&mut output, "metadata");
+            for (key, value) in std::mem::take(metadata) {
+                metadata.insert(mime_type(&key).to_string(), value);
+            }
+            output.insert("data".to_string(), Value::Object(data));
+        }
+        Some("pyerr") => {
+            output.insert("output_type".to_string(), Value::from("error"));
+        }
+        Some("stream") => {
+            let name = output.remove("stream").unwrap_or(Value::from("stdout"));
+            output.insert("name".to_string(), name);
+        }
+        _ => {}
+    }
+    Value::Object(output)
+}
+
+fn mime_type(key: &str) -> &str {
+    MIME_TYPES
+        .iter()
+        .find(|(v3, _)| *v3 == key)
+        .map_or(key, |(_, mime_type)| mime_type)
+}
+
+/// The object at `key` in `object`, replacing any other value with an empty object.
+fn object_entry<'a>(object: &'a mut Map<String, Value>, key: &str) -> &'a mut Map<String, Value> {
+    let entry = object
+        .entry(key)
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !entry.is_object() {
+        *entry = Value::Object(Map::new());
+    }
+    entry.as_object_mut().expect("the entry is an object")
+}
+
+/// A multiline string, stored either as a string or as a list of lines.
+fn text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrade_v3_notebooks() {
+        let notebook = r#"{
+ "metadata": {"name": "demo", "signature": "sha256:0"},
+ "nbformat": 3,
+ "nbformat_minor": 0,
+ "worksheets": [
+  {
+   "cells": [
+    {"cell_type": "heading", "level": 2, "metadata": {}, "source": ["A\n", "title"]},
+    {"cell_type": "code", "collapsed": false, "input": ["1"], "language": "python", "metadata": {}, "outputs": [
+     {"output_type": "stream", "stream": "stdout", "text": ["1\n"]},
+     {"output_type": "pyout", "prompt_number": 1, "text": ["1"], "png": "iVBOR", "metadata": {"png": {"width": 1}}},
+     {"output_type": "pyerr", "ename": "E", "evalue": "", "traceback": []}
+    ], "prompt_number": 1}
+   ],
+   "metadata": {}
+  }
+ ]
+}"#;
+        let upgraded = upgrade(notebook.as_bytes()).unwrap().unwrap();
+        assert_eq!(
+            String::from_utf8(upgraded).unwrap(),
+            r###"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "id": "cell-0",
+   "metadata": {},
+   "source": "## A title"
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "id": "cell-1",
+   "metadata": {
+    "collapsed": false
+   },
+   "outputs": [
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "1\n"
+     ]
+    },
+    {
+     "data": {
+      "image/png": "iVBOR",
+      "text/plain": [
+       "1"
+      ]
+     },
+     "execution_count": 1,
+     "metadata": {
+      "image/png": {
+       "width": 1
+      }
+     },
+     "output_type": "execute_result"
+    },
+    {
+     "ename": "E",
+     "evalue": "",
+     "output_type": "error",
+     "traceback": []
+    }
+   ],
+   "source": [
+    "1"
+   ]
+  }
+ ],
+ "metadata": {
+  "orig_nbformat": 3,
+  "orig_nbformat_minor": 0
+ },
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"###
+        );
+
+        assert!(upgrade(br#"{"cells": [], "nbformat": 4}"#)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,327 @@
+//! Structural validation of notebooks as they are read, so that other JSON files are not rewritten.
+
+use crate::layout::{Layout, LAYOUTS};
+use crate::path::{JsonPath, Segment};
+use crate::rules::PathPattern;
+use json_event_parser_witespace::{JsonEvent, JsonReader, TextPosition};
+use std::fmt;
+use std::io::BufRead;
+
+/// The cell types of nbformat 4, then those of nbformat 3.
+const CELL_TYPES: [&[&str]; 2] = [
+    &["code", "markdown", "raw"],
+    &["code", "markdown", "raw", "heading"],
+];
+/// The output types of nbformat 4, then those of nbformat 3.
+const OUTPUT_TYPES: [&[&str]; 2] = [
+    &["execute_result", "display_data", "stream", "error"],
+    &["pyout", "display_data", "stream", "pyerr"],
+];
+
+/// A notebook that does not have the structure of nbformat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub message: String,
+    pub position: TextPosition,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not a notebook: {} at {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Checks that the notebook read from `reader` has the structure of nbformat.
+pub fn validate_notebook<R: BufRead>(reader: R) -> anyhow::Result<()> {
+    let mut json_reader = JsonReader::from_reader(reader);
+    let mut buffer = vec![];
+    let mut validator = Validator::default();
+    loop {
+        let start = json_reader.position();
+        let event = json_reader.read_event(&mut buffer)?;
+        if event == JsonEvent::Eof {
+            return Ok(validator.finish(start)?);
+        }
+        validator.observe(&event, start)?;
+    }
+}
+
+#[derive(Debug)]
+enum Frame {
+    Root {
+        keys: Vec<String>,
+        nbformat: Option<u64>,
+    },
+    Cell {
+        path: String,
+        has_type: bool,
+    },
+    Output {
+        path: String,
+        has_type: bool,
+    },
+    Other,
+}
+
+/// The patterns of the values checked, for one cell layout.
+#[derive(Debug)]
+struct Patterns {
+    cells: PathPattern,
+    cell: &'static PathPattern,
+    cell_type: PathPattern,
+    outputs: PathPattern,
+    output: PathPattern,
+    output_type: PathPattern,
+}
+
+impl Patterns {
+    fn new(layout: &Layout) -> Self {
+        let cell = layout.cells_pattern();
+        let segments = cell.segments();
+        Self {
+            cells: PathPattern::new(segments[..segments.len() - 1].to_vec()),
+            cell,
+            cell_type: layout.cell_pattern("cell_type"),
+            outputs: layout.cell_pattern("outputs"),
+            output: layout.cell_pattern("outputs[*]"),
+            output_type: layout.cell_pattern("outputs[*].output_type"),
+        }
+    }
+}
+
+/// Follows the event stream to check that the notebook has the structure of nbformat: the root is
+/// an object with `nbformat`, `nbformat_minor`, `metadata` and `cells`, or `worksheets` in
+/// nbformat 3, each cell has a known `cell_type` and each output a known `output_type`.
+#[derive(Debug)]
+pub(crate) struct Validator {
+    path: JsonPath,
+    frames: Vec<Frame>,
+    patterns: Vec<Patterns>,
+    done: bool,
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        Self {
+            path: JsonPath::default(),
+            frames: vec![],
+            patterns: LAYOUTS.iter().map(Patterns::new).collect(),
+            done: false,
+        }
+    }
+}
+
+impl Validator {
+    /// Checks `event`, read at `position`.
+    pub fn observe(
+        &mut self,
+        event: &JsonEvent,
+        position: TextPosition,
+    ) -> Result<(), ValidationError> {
+        let error = |message: String| ValidationError { message, position };
+        match event {
+            JsonEvent::WhiteSpace(_) | JsonEvent::NextObjectValue | JsonEvent::NextArrayValue => {}
+            JsonEvent::ObjectKey(key) => {
+                if let (1, Some(Frame::Root { keys, .. })) =
+                    (self.frames.len(), self.frames.last_mut())
+                {
+                    keys.push(key.to_string());
+                }
+            }
+            JsonEvent::EndObject | JsonEvent::EndArray => match self.frames.pop() {
+                Some(Frame::Root { keys, nbformat }) => {
+                    let cells = if nbformat == Some(3) {
+                        "worksheets"
+                    } else {
+                        "cells"
+                    };
+                    for key in ["nbformat", "nbformat_minor", "metadata", cells] {
+                        if !keys.iter().any(|k| k == key) {
+                            return Err(error(format!("missing `{}`", key)));
+                        }
+                    }
+                    self.done = true;
+                }
+                Some(Frame::Cell { path, has_type }) if !has_type => {
+                    return Err(error(format!("missing `cell_type` in {}", path)));
+                }
+                Some(Frame::Output { path, has_type }) if !has_type => {
+                    return Err(error(format!("missing `output_type` in {}", path)));
+                }
+                _ => {}
+            },
+            JsonEvent::Eof => {}
+            _ => self.check_value(event).map_err(error)?,
+        }
+        self.path.observe(event);
+        Ok(())
+    }
+
+    /// Checks a value starting with `event`, at the current path.
+    fn check_value(&mut self, event: &JsonEvent) -> Result<(), String> {
+        let path = self.path.segments();
+        let is_object = *event == JsonEvent::StartObject;
+        let is_array = *event == JsonEvent::StartArray;
+        let mut frame = Frame::Other;
+
+        if self.frames.is_empty() {
+            if self.done || !is_object {
+                return Err("expected a single JSON object".to_string());
+            }
+            frame = Frame::Root {
+                keys: vec![],
+                nbformat: None,
+            };
+        } else if let [Segment::Key(key)] = path {
+            match (key.as_str(), event) {
+                ("nbformat" | "nbformat_minor", JsonEvent::Number(number)) => {
+                    let number = number
+                        .parse::<u64>()
+                        .map_err(|_| format!("`{}` must be an integer", key))?;
+                    if let Some(Frame::Root { nbformat, .. }) = self.frames.first_mut() {
+                        if key == "nbformat" {
+                            *nbformat = Some(number);
+                        }
+                    }
+                }
+                ("nbformat" | "nbformat_minor", _) => {
+                    return Err(format!("`{}` must be an integer", key));
+                }
+                ("metadata", _) if !is_object => return Err("`metadata` must be an object".into()),
+                ("cells" | "worksheets", _) if !is_array => {
+                    return Err(format!("`{}` must be an array", key));
+                }
+                _ => {}
+            }
+        } else {
+            for (patterns, (cell_types, output_types)) in self
+                .patterns
+                .iter()
+                .zip(CELL_TYPES.iter().zip(OUTPUT_TYPES.iter()))
+            {
+                let check_type = |what: &str, types: &[&str]| match event {
+                    JsonEvent::String(value) if types.contains(&value.as_ref()) => Ok(()),
+                    JsonEvent::String(value) => Err(format!(
+                        "unknown {} `{}` at {}, expected one of {}",
+                        what,
+                        value,
+                        self.path,
+                        types.join(", ")
+                    )),
+                    _ => Err(format!("{} at {} must be a string", what, self.path)),
+                };
+                if patterns.cells.matches(path) && !is_array {
+                    return Err(format!("{} must be an array", self.path));
+                } else if patterns.cell.matches(path) {
+                    if !is_object {
+                        return Err(format!("cell {} must be an object", self.path));
+                    }
+                    frame = Frame::Cell {
+                        path: self.path.to_string(),
+                        has_type: false,
+                    };
+                } else if patterns.cell_type.matches(path) {
+                    check_type("cell type", cell_types)?;
+                    if let Some(Frame::Cell { has_type, .. }) = self.frames.last_mut() {
+                        *has_type = true;
+                    }
+                } else if patterns.outputs.matches(path) && !is_array {
+                    return Err(format!("{} must be an array", self.path));
+                } else if patterns.output.matches(path) {
+                    if !is_object {
+                        return Err(format!("output {} must be an object", self.path));
+                    }
+                    frame = Frame::Output {
+                        path: self.path.to_string(),
+                        has_type: false,
+                    };
+                } else if patterns.output_type.matches(path) {
+                    check_type("output type", output_types)?;
+                    if let Some(Frame::Output { has_type, .. }) = self.frames.last_mut() {
+                        *has_type = true;
+                    }
+                }
+            }
+        }
+
+        if is_object || is_array {
+            self.frames.push(frame);
+        }
+        Ok(())
+    }
+
+    /// Checks that a whole notebook was read, `position` being the end of the input.
+    pub fn finish(&self, position: TextPosition) -> Result<(), ValidationError> {
+        if self.done {
+            Ok(())
+        } else {
+            Err(ValidationError {
+                message: "expected a JSON object".to_string(),
+                position,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(notebook: &str) -> Result<(), String> {
+        validate_notebook(notebook.as_bytes()).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn accept_notebooks() {
+        let v4 = r#"{
+ "cells": [
+  {"cell_type": "code", "metadata": {}, "outputs": [{"output_type": "stream", "name": "stdout", "text": "1"}], "source": ""},
+  {"cell_type": "markdown", "metadata": {}, "source": ""}
+ ],
+ "metadata": {},
+ "nbformat": 4,
+ "nbformat_minor": 5
+}"#;
+        assert_eq!(validate(v4), Ok(()));
+        let v3 = r#"{"metadata": {}, "nbformat": 3, "nbformat_minor": 0, "worksheets": [{"cells": [{"cell_type": "heading", "level": 1, "source": "A"}, {"cell_type": "code", "outputs": [{"output_type": "pyout", "text": "1"}]}]}]}"#;
+        assert_eq!(validate(v3), Ok(()));
+    }
+
+    #[test]
+    fn reject_other_json() {
+        assert_eq!(
+            validate("[1, 2]"),
+            Err("not a notebook: expected a single JSON object at line 1, column 1".to_string())
+        );
+        assert_eq!(
+            validate("{\n \"a\": 1\n}"),
+            Err("not a notebook: missing `nbformat` at line 3, column 1".to_string())
+        );
+        assert_eq!(
+            validate(r#"{"metadata": {}, "nbformat": 4, "nbformat_minor": 5}"#),
+            Err("not a notebook: missing `cells` at line 1, column 52".to_string())
+        );
+        assert_eq!(
+            validate(r#"{"cells": {}, "metadata": {}, "nbformat": 4, "nbformat_minor": 5}"#),
+            Err("not a notebook: `cells` must be an array at line 1, column 11".to_string())
+        );
+        assert_eq!(
+            validate(
+                "{\"metadata\": {}, \"nbformat\": 4, \"nbformat_minor\": 5,\n \"cells\": [\n  {\"cell_type\": \"codé\"}]}"
+            ),
+            Err("not a notebook: unknown cell type `codé` at cells[0].cell_type, expected one of code, markdown, raw at line 3, column 17".to_string())
+        );
+        assert_eq!(
+            validate(
+                r#"{"metadata": {}, "nbformat": 4, "nbformat_minor": 5, "cells": [{"metadata": {}}]}"#
+            ),
+            Err("not a notebook: missing `cell_type` in cells[0] at line 1, column 79".to_string())
+        );
+        assert_eq!(
+            validate(r#"{"metadata": {}, "nbformat": 4, "nbformat_minor": 5, "cells": [{"cell_type": "code", "outputs": [{"output_type": "pyout"}]}]}"#),
+            Err("not a notebook: unknown output type `pyout` at cells[0].outputs[0].output_type, expected one of execute_result, display_data, stream, error at line 1, column 114".to_string())
+        );
+    }
+}